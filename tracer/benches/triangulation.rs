@@ -0,0 +1,63 @@
+use std::f32::consts::TAU;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use tracer::{triangulator::Triangulator, vec::Vec2};
+
+fn wavy_outline(size: usize) -> Vec<Vec2> {
+    (0..size)
+        .map(|i| {
+            let angle = i as f32 / size as f32 * TAU * 0.9;
+            let radius = if i % 2 == 0 { 1.0 } else { 0.8 };
+            Vec2::new((angle.cos() * radius, angle.sin() * radius))
+        })
+        .collect()
+}
+
+fn drawn_selection(outline: &[Vec2]) -> Triangulator {
+    let mut triangulator = Triangulator::new();
+    outline.iter().for_each(|point| {
+        triangulator.add(*point);
+    });
+    triangulator
+}
+
+fn cursor_movement(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cursor_movement");
+
+    for size in [100, 500, 1000] {
+        let outline = wavy_outline(size);
+        let cursors: Vec<_> = (0..16)
+            .map(|i| {
+                let angle = TAU * (0.92 + i as f32 * 0.004);
+                Vec2::new((angle.cos(), angle.sin()))
+            })
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("full", size), &outline, |b, outline| {
+            let mut triangulator = drawn_selection(outline);
+            let mut cursor = cursors.iter().cycle();
+            b.iter(|| {
+                triangulator.update(*cursor.next().unwrap());
+                black_box(triangulator.triangulate().map(|(_, indices)| indices.len()));
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("incremental", size),
+            &outline,
+            |b, outline| {
+                let mut triangulator = drawn_selection(outline);
+                let mut cursor = cursors.iter().cycle();
+                b.iter(|| {
+                    triangulator.update(*cursor.next().unwrap());
+                    black_box(triangulator.triangulate_incremental());
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, cursor_movement);
+criterion_main!(benches);
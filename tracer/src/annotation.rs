@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use crate::{triangulator::Triangulator, vec::Vec2};
+
+pub type AnnotationId = u32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelClass {
+    pub name: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub id: AnnotationId,
+    pub label: usize,
+    pub polygon: Vec<Vec2>,
+    pub holes: Vec<Vec<Vec2>>,
+    pub author: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnnotatedImage {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub annotations: Vec<Annotation>,
+    /// Parts of the annotation files of the image the model has no place for,
+    /// keyed by format, so saving can write them back unchanged.
+    pub retained: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Project {
+    pub labels: Vec<LabelClass>,
+    pub images: Vec<AnnotatedImage>,
+    /// Recorded on every annotation created from now on.
+    pub author: String,
+    next_id: AnnotationId,
+}
+
+/// Maps image pixel coordinates (origin in the top left corner, y pointing
+/// down) onto the editor space of the quad the image is drawn on, which is
+/// centered at the origin with y pointing up.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageFrame {
+    pub resolution: (f32, f32),
+    pub half_extent: (f32, f32),
+}
+
+impl LabelClass {
+    /// Creates the label with a colour picked from its position in the label
+    /// list, stepping the hue by the golden ratio to keep neighbours distinct.
+    pub fn new(name: &str, index: usize) -> Self {
+        let hue = (0.6 + index as f32 * 0.618_034).fract() * 6.0;
+        let (saturation, value) = (0.75, 0.9);
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |c: f32| ((c + value - chroma) * 255.0).round() as u8;
+
+        LabelClass {
+            name: String::from(name),
+            color: [channel(r), channel(g), channel(b)],
+        }
+    }
+}
+
+impl Annotation {
+    pub fn bounding_box(&self) -> Option<(Vec2, Vec2)> {
+        let first = *self.polygon.first()?;
+        Some(
+            self.polygon
+                .iter()
+                .fold((first, first), |(min, max), point| {
+                    (
+                        Vec2::new((min.x.min(point.x), min.y.min(point.y))),
+                        Vec2::new((max.x.max(point.x), max.y.max(point.y))),
+                    )
+                }),
+        )
+    }
+
+    pub fn area(&self) -> f32 {
+        let mut triangulator = Triangulator::from_outline(&self.polygon, &self.holes);
+        triangulator
+            .triangulate()
+            .map_or(0.0, |(vertices, indices)| {
+                Triangulator::area(vertices, &indices)
+            })
+    }
+}
+
+impl AnnotatedImage {
+    pub fn new(path: &str, width: u32, height: u32) -> Self {
+        AnnotatedImage {
+            path: String::from(path),
+            width,
+            height,
+            annotations: vec![],
+            retained: BTreeMap::new(),
+        }
+    }
+
+    pub fn annotation(&self, id: AnnotationId) -> Option<&Annotation> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.id == id)
+    }
+
+    pub fn annotation_mut(&mut self, id: AnnotationId) -> Option<&mut Annotation> {
+        self.annotations
+            .iter_mut()
+            .find(|annotation| annotation.id == id)
+    }
+}
+
+impl Project {
+    pub fn new(labels: &[&str]) -> Self {
+        Project {
+            labels: labels
+                .iter()
+                .enumerate()
+                .map(|(index, name)| LabelClass::new(name, index))
+                .collect(),
+            images: vec![],
+            author: String::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn add_image(&mut self, path: &str, width: u32, height: u32) -> usize {
+        match self.find_image(path) {
+            Some(index) => {
+                self.images[index].width = width;
+                self.images[index].height = height;
+                index
+            }
+            None => {
+                self.images.push(AnnotatedImage::new(path, width, height));
+                self.images.len() - 1
+            }
+        }
+    }
+
+    pub fn find_image(&self, path: &str) -> Option<usize> {
+        self.images.iter().position(|image| image.path == path)
+    }
+
+    pub fn image(&self, index: usize) -> Option<&AnnotatedImage> {
+        self.images.get(index)
+    }
+
+    pub fn image_mut(&mut self, index: usize) -> Option<&mut AnnotatedImage> {
+        self.images.get_mut(index)
+    }
+
+    pub fn find_label(&self, name: &str) -> Option<usize> {
+        self.labels.iter().position(|label| label.name == name)
+    }
+
+    pub fn add_label(&mut self, name: &str) -> usize {
+        self.find_label(name).unwrap_or_else(|| {
+            self.labels.push(LabelClass::new(name, self.labels.len()));
+            self.labels.len() - 1
+        })
+    }
+
+    /// Builds an annotation with the next free id without adding it to any
+    /// image, the id is only taken once the annotation is inserted.
+    pub fn new_annotation(&self, label: usize, polygon: Vec<Vec2>) -> Annotation {
+        Annotation {
+            id: self.next_id,
+            label,
+            polygon,
+            holes: vec![],
+            author: self.author.clone(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn create_annotation(
+        &mut self,
+        image: usize,
+        label: usize,
+        polygon: Vec<Vec2>,
+    ) -> Option<AnnotationId> {
+        self.insert_annotation(image, self.new_annotation(label, polygon))
+    }
+
+    pub fn insert_annotation(
+        &mut self,
+        image: usize,
+        annotation: Annotation,
+    ) -> Option<AnnotationId> {
+        let id = annotation.id;
+        self.images.get_mut(image)?.annotations.push(annotation);
+        self.next_id = self.next_id.max(id + 1);
+        Some(id)
+    }
+
+    pub fn remove_annotation(&mut self, image: usize, id: AnnotationId) -> Option<Annotation> {
+        let annotations = &mut self.images.get_mut(image)?.annotations;
+        let index = annotations
+            .iter()
+            .position(|annotation| annotation.id == id)?;
+        Some(annotations.remove(index))
+    }
+}
+
+impl ImageFrame {
+    pub fn to_pixels(&self, pos: Vec2) -> Vec2 {
+        let (width, height) = self.resolution;
+        let (half_width, half_height) = self.half_extent;
+        Vec2::new((
+            (pos.x + half_width) / (2.0 * half_width) * width,
+            (half_height - pos.y) / (2.0 * half_height) * height,
+        ))
+    }
+
+    pub fn to_editor(&self, pixel: Vec2) -> Vec2 {
+        let (width, height) = self.resolution;
+        let (half_width, half_height) = self.half_extent;
+        Vec2::new((
+            pixel.x / width * 2.0 * half_width - half_width,
+            half_height - pixel.y / height * 2.0 * half_height,
+        ))
+    }
+}
+
+/// Shapes and projects shared by the tests of the annotation model, the
+/// formats and the history.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    pub(crate) fn rectangle(x: f32, y: f32, width: f32, height: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new((x, y)),
+            Vec2::new((x + width, y)),
+            Vec2::new((x + width, y + height)),
+            Vec2::new((x, y + height)),
+        ]
+    }
+
+    pub(crate) fn square(x: f32, y: f32, size: f32) -> Vec<Vec2> {
+        rectangle(x, y, size, size)
+    }
+
+    /// A project labelled `jp2` and `gmd` holding one image, returned with
+    /// the index of the image.
+    pub(crate) fn project_with_image(width: u32, height: u32) -> (Project, usize) {
+        let mut project = Project::new(&["jp2", "gmd"]);
+        let image = project.add_image("tracer/images/boomer.jpg", width, height);
+        (project, image)
+    }
+
+    /// A 640x480 image holding a `gmd` square and an empty `jp2` annotation.
+    pub(crate) fn project_with_square() -> Project {
+        let (mut project, image) = project_with_image(640, 480);
+        project
+            .create_annotation(image, 1, square(10.0, 20.0, 40.0))
+            .unwrap();
+        project.create_annotation(image, 0, vec![]).unwrap();
+        project
+    }
+
+    /// A 640x480 image holding a `gmd` triangle with fractional corners,
+    /// returned with the id of the triangle.
+    pub(crate) fn project_with_triangle() -> (Project, AnnotationId) {
+        let (mut project, image) = project_with_image(640, 480);
+        let triangle = vec![
+            Vec2::new((10.5, 20.0)),
+            Vec2::new((50.0, 20.25)),
+            Vec2::new((30.0, 60.75)),
+        ];
+        let id = project.create_annotation(image, 1, triangle).unwrap();
+        (project, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn annotation_ids_are_unique_across_images() {
+        let mut project = Project::new(&["jp2", "gmd"]);
+        let first = project.add_image("first.jpg", 640, 480);
+        let second = project.add_image("second.jpg", 800, 600);
+
+        let ids = [
+            project.create_annotation(first, 0, vec![]).unwrap(),
+            project.create_annotation(second, 1, vec![]).unwrap(),
+            project.create_annotation(first, 1, vec![]).unwrap(),
+        ];
+        assert_eq!(ids, [1, 2, 3]);
+
+        let removed = project.remove_annotation(first, ids[0]).unwrap();
+        assert_eq!(removed.id, ids[0]);
+        assert_eq!(project.image(first).unwrap().annotations.len(), 1);
+        assert_eq!(project.create_annotation(second, 0, vec![]), Some(4));
+    }
+
+    #[test]
+    fn adding_the_same_image_twice_reuses_it() {
+        let mut project = Project::new(&[]);
+        assert_eq!(project.add_image("image.jpg", 640, 480), 0);
+        assert_eq!(project.add_image("image.jpg", 640, 480), 0);
+        assert_eq!(project.images.len(), 1);
+        assert_eq!(project.create_annotation(1, 0, vec![]), None);
+    }
+
+    #[test]
+    fn label_colors_are_distinct() {
+        let project = Project::new(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let colors: Vec<_> = project.labels.iter().map(|label| label.color).collect();
+        colors.iter().enumerate().for_each(|(index, color)| {
+            assert!(!colors[index + 1..].contains(color));
+        });
+    }
+
+    #[test]
+    fn image_frame_maps_corners_to_pixels() {
+        let frame = ImageFrame {
+            resolution: (640.0, 480.0),
+            half_extent: (1.2, 0.9),
+        };
+
+        let top_left = frame.to_pixels(Vec2::new((-1.2, 0.9)));
+        let bottom_right = frame.to_pixels(Vec2::new((1.2, -0.9)));
+        assert_eq!((top_left.x, top_left.y), (0.0, 0.0));
+        assert_eq!((bottom_right.x, bottom_right.y), (640.0, 480.0));
+
+        let center = frame.to_editor(Vec2::new((320.0, 240.0)));
+        assert_eq!((center.x, center.y), (0.0, 0.0));
+    }
+}
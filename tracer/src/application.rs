@@ -1,6 +1,11 @@
 use std::io::Write;
 
-use crate::ui_element::ui_root::UiRoot;
+use crate::{
+    formats::FormatError,
+    input::InputEvent,
+    keymap::KeymapError,
+    ui_element::ui_root::{Arguments, Export, Import, UiRoot},
+};
 use glad_gl::gl;
 
 #[derive(Debug)]
@@ -8,27 +13,41 @@ pub struct AppError {
     pub error_msg: String,
 }
 
-#[derive(Clone, Copy)]
-pub enum MouseEvent {
-    Movement,
-    LeftClick,
-    RightClick,
-    Scroll(f32),
+impl From<FormatError> for AppError {
+    fn from(from: FormatError) -> AppError {
+        AppError {
+            error_msg: from.error_msg,
+        }
+    }
+}
+
+impl From<KeymapError> for AppError {
+    fn from(from: KeymapError) -> AppError {
+        AppError {
+            error_msg: from.error_msg,
+        }
+    }
 }
 
 pub trait Application {
     fn on_init(&mut self) {}
-    fn handle_event(&mut self, _pos: (f32, f32), _event: MouseEvent) {}
+    fn handle_event(&mut self, _pos: (f32, f32), _event: InputEvent) {}
 
     fn get_title(&self) -> &'static str;
     fn get_resolution(&self) -> (u32, u32);
     fn render(&mut self) {}
+    fn should_close(&self) -> bool {
+        false
+    }
+    fn on_exit(&mut self) {}
 }
 
 pub struct Program {
     pub title: &'static str,
     pub width: u32,
     pub height: u32,
+    imports: Vec<Import>,
+    exports: Vec<Export>,
     ui_root: Option<UiRoot>,
 }
 
@@ -39,9 +58,17 @@ impl Program {
             title,
             width,
             height,
+            imports: vec![],
+            exports: vec![],
             ui_root: None,
         }
     }
+
+    pub fn with_arguments(mut self, arguments: Arguments) -> Self {
+        self.imports = arguments.imports;
+        self.exports = arguments.exports;
+        self
+    }
 }
 
 impl Application for Program {
@@ -65,15 +92,32 @@ impl Application for Program {
     }
 
     fn on_init(&mut self) {
-        self.ui_root = match UiRoot::build() {
+        self.ui_root = match UiRoot::build(&self.imports) {
             Ok(ui_root) => Some(ui_root),
-            _ => panic!("Failed to initialize UI context"),
+            Err(err) => panic!("Failed to initialize UI context:\n{}", err.error_msg),
         }
     }
 
-    fn handle_event(&mut self, pos: (f32, f32), event: MouseEvent) {
+    fn handle_event(&mut self, pos: (f32, f32), event: InputEvent) {
         if let Some(ui_root) = &mut self.ui_root {
             ui_root.handle(pos, event);
         }
     }
+
+    fn should_close(&self) -> bool {
+        self.ui_root
+            .as_ref()
+            .is_some_and(|ui_root| ui_root.should_close())
+    }
+
+    fn on_exit(&mut self) {
+        if let Some(ui_root) = &self.ui_root {
+            if let Err(err) = ui_root.save() {
+                eprintln!("Failed to save annotations:\n{}", err.error_msg);
+            }
+            if let Err(err) = ui_root.export(&self.exports) {
+                eprintln!("Failed to export annotations:\n{}", err.error_msg);
+            }
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+pub mod coco;
+pub mod cvat;
+pub mod geojson;
+pub mod labelme;
+pub mod mask;
+pub mod svg;
+pub mod voc;
+pub mod yolo;
+
+#[derive(Debug)]
+pub struct FormatError {
+    pub error_msg: String,
+}
+
+impl From<io::Error> for FormatError {
+    fn from(from: io::Error) -> FormatError {
+        FormatError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(from: serde_json::Error) -> FormatError {
+        FormatError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+impl From<image::ImageError> for FormatError {
+    fn from(from: image::ImageError) -> FormatError {
+        FormatError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+impl From<quick_xml::DeError> for FormatError {
+    fn from(from: quick_xml::DeError) -> FormatError {
+        FormatError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Path of the per-image annotation file in `directory`, named after the image
+/// with its extension replaced. Dots in the stem are kept, so `a.v2.jpg` and
+/// `a.v3.jpg` get files of their own.
+fn label_path(directory: &Path, image_path: &str, extension: &str) -> PathBuf {
+    let stem = Path::new(image_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(image_path);
+    directory.join(format!("{stem}.{extension}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn label_paths_keep_dotted_stems() {
+        let directory = Path::new("labels");
+        assert_eq!(
+            label_path(directory, "images/a.v2.jpg", "xml"),
+            directory.join("a.v2.xml")
+        );
+        assert_ne!(
+            label_path(directory, "a.v2.jpg", "png"),
+            label_path(directory, "a.v3.jpg", "png")
+        );
+        assert_eq!(
+            label_path(directory, "boomer.jpg", "txt"),
+            directory.join("boomer.txt")
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::{collections::HashMap, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, Project},
+    vec::Vec2,
+};
+
+use self::rle::CocoRle;
+
+use super::{
+    file_name,
+    mask::{
+        contour::{simplify, trace},
+        Mask,
+    },
+    FormatError,
+};
+
+pub mod rle;
+
+/// Tolerance in pixels used when turning RLE masks back into polygons.
+const RLE_TOLERANCE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CocoEncoding {
+    /// Polygon lists, annotations with holes fall back to RLE since
+    /// polygons cannot describe them.
+    Polygons,
+    /// Compressed RLE masks for every annotation.
+    Rle,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CocoDataset {
+    pub images: Vec<CocoImage>,
+    pub categories: Vec<CocoCategory>,
+    pub annotations: Vec<CocoAnnotation>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CocoImage {
+    pub id: u32,
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CocoCategory {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub supercategory: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: u32,
+    pub image_id: u32,
+    pub category_id: u32,
+    pub segmentation: CocoSegmentation,
+    pub area: f32,
+    pub bbox: [f32; 4],
+    #[serde(default)]
+    pub iscrowd: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CocoSegmentation {
+    Polygons(Vec<Vec<f32>>),
+    Rle(CocoRle),
+}
+
+/// Builds a COCO dataset out of the project. Image and category ids are the
+/// 1-based indices into the project, annotation ids are kept as they are.
+pub fn export(project: &Project, encoding: CocoEncoding) -> CocoDataset {
+    CocoDataset {
+        images: project
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| CocoImage {
+                id: index as u32 + 1,
+                file_name: file_name(&image.path),
+                width: image.width,
+                height: image.height,
+            })
+            .collect(),
+        categories: project
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| CocoCategory {
+                id: index as u32 + 1,
+                name: label.name.clone(),
+                supercategory: String::new(),
+            })
+            .collect(),
+        annotations: project
+            .images
+            .iter()
+            .enumerate()
+            .flat_map(|(index, image)| export_image(index as u32 + 1, image, encoding))
+            .collect(),
+    }
+}
+
+pub fn save(project: &Project, filename: &str, encoding: CocoEncoding) -> Result<(), FormatError> {
+    let json = serde_json::to_string_pretty(&export(project, encoding))?;
+    fs::write(filename, json)?;
+    Ok(())
+}
+
+pub fn load(filename: &str) -> Result<CocoDataset, FormatError> {
+    let json = fs::read_to_string(filename)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Adds the polygons of the dataset to the images of the project with the
+/// same file name, creating one annotation per polygon. RLE masks are traced
+/// into one annotation per connected region, holes included. Categories are
+/// matched to project labels by name, unknown ones are appended to the
+/// labels. Images the project does not know about are skipped. Returns the
+/// number of annotations created.
+pub fn import(project: &mut Project, dataset: &CocoDataset) -> usize {
+    let images: HashMap<u32, usize> = dataset
+        .images
+        .iter()
+        .filter_map(|image| {
+            let index = project
+                .images
+                .iter()
+                .position(|known| file_name(&known.path) == image.file_name)?;
+            Some((image.id, index))
+        })
+        .collect();
+    let labels: HashMap<u32, usize> = dataset
+        .categories
+        .iter()
+        .map(|category| (category.id, project.add_label(&category.name)))
+        .collect();
+
+    let mut imported = 0;
+    for annotation in &dataset.annotations {
+        let (Some(image), Some(label)) = (
+            images.get(&annotation.image_id),
+            labels.get(&annotation.category_id),
+        ) else {
+            continue;
+        };
+
+        let shapes = match &annotation.segmentation {
+            CocoSegmentation::Polygons(polygons) => polygons
+                .iter()
+                .map(|polygon| (unflatten(polygon), vec![]))
+                .collect(),
+            CocoSegmentation::Rle(rle) => rle_shapes(rle),
+        };
+
+        for (polygon, holes) in shapes {
+            if polygon.len() < 3 {
+                continue;
+            }
+            let Some(id) = project.create_annotation(*image, *label, polygon) else {
+                continue;
+            };
+            if let Some(annotation) = project
+                .image_mut(*image)
+                .and_then(|image| image.annotation_mut(id))
+            {
+                annotation.holes = holes;
+            }
+            imported += 1;
+        }
+    }
+    imported
+}
+
+fn rle_shapes(rle: &CocoRle) -> Vec<(Vec<Vec2>, Vec<Vec<Vec2>>)> {
+    let Ok(mask) = rle.decode(1) else {
+        return vec![];
+    };
+    trace(&mask)
+        .into_iter()
+        .map(|contour| {
+            (
+                simplify(&contour.outline, RLE_TOLERANCE),
+                contour
+                    .holes
+                    .iter()
+                    .map(|hole| simplify(hole, RLE_TOLERANCE))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+fn export_image(
+    image_id: u32,
+    image: &AnnotatedImage,
+    encoding: CocoEncoding,
+) -> impl Iterator<Item = CocoAnnotation> + '_ {
+    image
+        .annotations
+        .iter()
+        .filter_map(move |annotation| export_annotation(image_id, image, annotation, encoding))
+}
+
+fn export_annotation(
+    image_id: u32,
+    image: &AnnotatedImage,
+    annotation: &Annotation,
+    encoding: CocoEncoding,
+) -> Option<CocoAnnotation> {
+    if annotation.polygon.len() < 3 {
+        return None;
+    }
+    let (min, max) = annotation.bounding_box()?;
+
+    let (segmentation, area) = if encoding == CocoEncoding::Polygons && annotation.holes.is_empty()
+    {
+        (
+            CocoSegmentation::Polygons(vec![flatten(&annotation.polygon)]),
+            annotation.area(),
+        )
+    } else {
+        let mut mask = Mask::new(image.width, image.height);
+        mask.fill_annotation(annotation, 1);
+        let rle = CocoRle::encode(&mask).compress().ok()?;
+        let area = rle.area().ok()? as f32;
+        (CocoSegmentation::Rle(rle), area)
+    };
+
+    Some(CocoAnnotation {
+        id: annotation.id,
+        image_id,
+        category_id: annotation.label as u32 + 1,
+        segmentation,
+        area,
+        bbox: [min.x, min.y, max.x - min.x, max.y - min.y],
+        iscrowd: 0,
+    })
+}
+
+fn flatten(points: &[Vec2]) -> Vec<f32> {
+    points.iter().flat_map(|point| [point.x, point.y]).collect()
+}
+
+fn unflatten(coordinates: &[f32]) -> Vec<Vec2> {
+    coordinates
+        .chunks_exact(2)
+        .map(|point| Vec2::new((point[0], point[1])))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::{project_with_image, project_with_square, square};
+
+    #[test]
+    fn exports_images_categories_and_polygons() {
+        let dataset = export(&project_with_square(), CocoEncoding::Polygons);
+
+        assert_eq!(
+            dataset.images,
+            vec![CocoImage {
+                id: 1,
+                file_name: String::from("boomer.jpg"),
+                width: 640,
+                height: 480,
+            }]
+        );
+        assert_eq!(dataset.categories[1].id, 2);
+        assert_eq!(dataset.categories[1].name, "gmd");
+
+        assert_eq!(dataset.annotations.len(), 1);
+        let annotation = &dataset.annotations[0];
+        assert_eq!((annotation.image_id, annotation.category_id), (1, 2));
+        assert_eq!(
+            annotation.segmentation,
+            CocoSegmentation::Polygons(vec![vec![10.0, 20.0, 50.0, 20.0, 50.0, 60.0, 10.0, 60.0]])
+        );
+        assert_eq!(annotation.bbox, [10.0, 20.0, 40.0, 40.0]);
+        assert_eq!(annotation.area, 1600.0);
+    }
+
+    #[test]
+    fn annotations_with_holes_fall_back_to_rle() {
+        let mut project = project_with_square();
+        project.images[0].annotations[0].holes = vec![square(20.0, 30.0, 10.0)];
+
+        let dataset = export(&project, CocoEncoding::Polygons);
+        let annotation = &dataset.annotations[0];
+        assert_eq!(annotation.area, 1500.0);
+        let CocoSegmentation::Rle(rle) = &annotation.segmentation else {
+            panic!("expected an RLE segmentation");
+        };
+        assert_eq!(rle.size, [480, 640]);
+
+        let (mut imported, _) = project_with_image(640, 480);
+        assert_eq!(import(&mut imported, &dataset), 1);
+        let annotation = &imported.images[0].annotations[0];
+        assert_eq!(annotation.holes.len(), 1);
+        assert_eq!(annotation.area(), 1500.0);
+    }
+
+    #[test]
+    fn rle_export_survives_json() {
+        let dataset = export(&project_with_square(), CocoEncoding::Rle);
+        let json = serde_json::to_string(&dataset).unwrap();
+        assert!(json.contains("\"counts\":\""));
+
+        let parsed: CocoDataset = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, dataset);
+        assert_eq!(parsed.annotations[0].area, 1600.0);
+    }
+
+    #[test]
+    fn reads_uncompressed_crowd_annotations() {
+        let json = r#"{
+            "images": [{"id": 7, "file_name": "boomer.jpg", "width": 4, "height": 3}],
+            "categories": [{"id": 3, "name": "gmd"}],
+            "annotations": [{
+                "id": 1, "image_id": 7, "category_id": 3, "iscrowd": 1,
+                "segmentation": {"size": [3, 4], "counts": [3, 6, 3]},
+                "area": 6, "bbox": [1, 0, 2, 3]
+            }]
+        }"#;
+        let dataset: CocoDataset = serde_json::from_str(json).unwrap();
+
+        let (mut project, _) = project_with_image(4, 3);
+        assert_eq!(import(&mut project, &dataset), 1);
+        let annotation = &project.images[0].annotations[0];
+        assert_eq!(annotation.label, 1);
+        assert_eq!(
+            annotation
+                .bounding_box()
+                .map(|(min, max)| (min.x, max.x, max.y)),
+            Some((1.0, 3.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn import_restores_exported_polygons() {
+        let dataset = export(&project_with_square(), CocoEncoding::Polygons);
+
+        let mut project = Project::new(&["gmd"]);
+        let image = project.add_image("other/directory/boomer.jpg", 640, 480);
+        project.add_image("unrelated.jpg", 640, 480);
+
+        assert_eq!(import(&mut project, &dataset), 1);
+        let annotation = &project.images[image].annotations[0];
+        assert_eq!(annotation.label, 0);
+        assert_eq!(
+            annotation.polygon,
+            project_with_square().images[0].annotations[0].polygon
+        );
+        assert!(project.images[1].annotations.is_empty());
+    }
+
+    #[test]
+    fn import_appends_unknown_categories() {
+        let dataset = export(&project_with_square(), CocoEncoding::Polygons);
+        let mut project = Project::new(&["other"]);
+        project.add_image("boomer.jpg", 640, 480);
+
+        import(&mut project, &dataset);
+        assert_eq!(project.find_label("gmd"), Some(2));
+        assert_eq!(project.images[0].annotations[0].label, 2);
+    }
+}
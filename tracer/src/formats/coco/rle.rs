@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use crate::formats::{mask::Mask, FormatError};
+
+/// Run-length encoded binary mask as used by COCO. Runs go over the pixels in
+/// column-major order and alternate between background and foreground,
+/// starting with background.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CocoRle {
+    /// Height and width of the mask, in that order.
+    pub size: [u32; 2],
+    pub counts: RleCounts,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RleCounts {
+    Uncompressed(Vec<u32>),
+    Compressed(String),
+}
+
+impl CocoRle {
+    /// Encodes the nonzero pixels of the mask.
+    pub fn encode(mask: &Mask) -> Self {
+        let (width, height) = (mask.width as usize, mask.height as usize);
+        let mut counts = vec![];
+        let mut current = false;
+        let mut run = 0;
+
+        for x in 0..width {
+            for y in 0..height {
+                let set = mask.data[y * width + x] != 0;
+                if set != current {
+                    counts.push(run);
+                    current = set;
+                    run = 0;
+                }
+                run += 1;
+            }
+        }
+        counts.push(run);
+
+        CocoRle {
+            size: [mask.height, mask.width],
+            counts: RleCounts::Uncompressed(counts),
+        }
+    }
+
+    /// Decodes into a mask holding `value` in the foreground pixels.
+    pub fn decode(&self, value: u16) -> Result<Mask, FormatError> {
+        let [height, width] = self.size;
+        let counts = self.counts()?;
+        let mut mask = Mask::new(width, height);
+
+        let mut pixel = 0;
+        for (index, count) in counts.iter().enumerate() {
+            let end = pixel + *count as usize;
+            if end > mask.data.len() {
+                return Err(FormatError {
+                    error_msg: String::from("RLE counts exceed the mask size"),
+                });
+            }
+            if index % 2 == 1 {
+                for pixel in pixel..end {
+                    let (x, y) = (pixel / height as usize, pixel % height as usize);
+                    mask.data[y * width as usize + x] = value;
+                }
+            }
+            pixel = end;
+        }
+        Ok(mask)
+    }
+
+    pub fn compress(&self) -> Result<Self, FormatError> {
+        Ok(CocoRle {
+            size: self.size,
+            counts: RleCounts::Compressed(compress_counts(&self.counts()?)),
+        })
+    }
+
+    pub fn counts(&self) -> Result<Vec<u32>, FormatError> {
+        match &self.counts {
+            RleCounts::Uncompressed(counts) => Ok(counts.clone()),
+            RleCounts::Compressed(counts) => decompress_counts(counts),
+        }
+    }
+
+    pub fn area(&self) -> Result<u32, FormatError> {
+        Ok(self.counts()?.iter().skip(1).step_by(2).sum())
+    }
+}
+
+/// Packs the counts into the LEB128-like string of the COCO API: every count
+/// from the third one on is stored as the difference to the count two places
+/// before it, in 5-bit groups offset into printable ASCII.
+fn compress_counts(counts: &[u32]) -> String {
+    let mut compressed = String::new();
+    for (index, count) in counts.iter().enumerate() {
+        let mut x = *count as i64;
+        if index > 2 {
+            x -= counts[index - 2] as i64;
+        }
+
+        let mut more = true;
+        while more {
+            let mut c = (x & 0x1f) as u8;
+            x >>= 5;
+            more = if c & 0x10 != 0 { x != -1 } else { x != 0 };
+            if more {
+                c |= 0x20;
+            }
+            compressed.push((c + 48) as char);
+        }
+    }
+    compressed
+}
+
+fn decompress_counts(compressed: &str) -> Result<Vec<u32>, FormatError> {
+    let invalid = || FormatError {
+        error_msg: format!("Invalid compressed RLE counts '{}'", compressed),
+    };
+    let bytes = compressed.as_bytes();
+    let mut counts: Vec<u32> = vec![];
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let mut x: i64 = 0;
+        let mut shift = 0;
+        loop {
+            let c = bytes
+                .get(position)
+                .and_then(|byte| byte.checked_sub(48))
+                .filter(|c| *c < 64 && shift < 60)
+                .ok_or_else(invalid)? as i64;
+            x |= (c & 0x1f) << shift;
+            position += 1;
+            shift += 5;
+            if c & 0x20 == 0 {
+                if c & 0x10 != 0 {
+                    x |= -1 << shift;
+                }
+                break;
+            }
+        }
+
+        if counts.len() > 2 {
+            x += counts[counts.len() - 2] as i64;
+        }
+        counts.push(u32::try_from(x).map_err(|_| invalid())?);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::formats::mask::test_support::mask_from_rows;
+    use proptest::prelude::*;
+
+    #[test]
+    fn encodes_in_column_major_order() {
+        let mask = mask_from_rows(&["0110", "0100", "0000"]);
+        let rle = CocoRle::encode(&mask);
+
+        assert_eq!(rle.size, [3, 4]);
+        assert_eq!(rle.counts, RleCounts::Uncompressed(vec![3, 2, 1, 1, 5]));
+        assert_eq!(rle.area().unwrap(), 3);
+        assert_eq!(rle.decode(1).unwrap(), mask);
+    }
+
+    #[test]
+    fn matches_the_coco_api_string_form() {
+        // String form of these runs as written by `rleToString` in the COCO API.
+        let rle = CocoRle {
+            size: [10, 10],
+            counts: RleCounts::Uncompressed(vec![22, 3, 7, 3, 7, 3, 55]),
+        };
+        let compressed = rle.compress().unwrap();
+        assert_eq!(
+            compressed.counts,
+            RleCounts::Compressed(String::from("f037000`1"))
+        );
+        assert_eq!(compressed.counts().unwrap(), rle.counts().unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let rle = CocoRle {
+            size: [2, 2],
+            counts: RleCounts::Uncompressed(vec![1, 5]),
+        };
+        assert!(rle.decode(1).is_err());
+        assert!(decompress_counts("\u{1}").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn compression_round_trips(counts in prop::collection::vec(0u32..100_000, 0..50)) {
+            let compressed = compress_counts(&counts);
+            prop_assert_eq!(decompress_counts(&compressed).unwrap(), counts);
+        }
+
+        #[test]
+        fn masks_round_trip(width in 1u32..12, height in 1u32..12, seed in any::<u64>()) {
+            let mut mask = Mask::new(width, height);
+            mask.data
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, pixel)| *pixel = ((seed >> (index % 64)) & 1) as u16);
+
+            let rle = CocoRle::encode(&mask).compress().unwrap();
+            prop_assert_eq!(rle.decode(1).unwrap(), mask);
+        }
+    }
+}
@@ -0,0 +1,499 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{Annotation, Project},
+    vec::Vec2,
+};
+
+use super::{file_name, labelme, FormatError};
+
+const CVAT_VERSION: &str = "1.1";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "annotations")]
+pub struct CvatAnnotations {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<CvatMeta>,
+    #[serde(rename = "image", default)]
+    pub images: Vec<CvatImage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<CvatTask>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatTask {
+    #[serde(default)]
+    pub labels: CvatLabels,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CvatLabels {
+    #[serde(rename = "label", default)]
+    pub labels: Vec<CvatLabel>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatLabel {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatImage {
+    #[serde(rename = "@id")]
+    pub id: u32,
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@width")]
+    pub width: u32,
+    #[serde(rename = "@height")]
+    pub height: u32,
+    #[serde(rename = "$value", default)]
+    pub shapes: Vec<CvatShape>,
+}
+
+/// Shapes CVAT stores inside an `<image>`. Tracks, masks and the other shape
+/// kinds the editor cannot represent are parsed as `Other` and skipped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CvatShape {
+    #[serde(rename = "polygon")]
+    Polygon(CvatPolygon),
+    #[serde(rename = "box")]
+    Box(CvatBox),
+    #[serde(rename = "points")]
+    Points(CvatPolygon),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatPolygon {
+    #[serde(rename = "@label")]
+    pub label: String,
+    #[serde(rename = "@occluded", default)]
+    pub occluded: u8,
+    #[serde(rename = "@points")]
+    pub points: String,
+    #[serde(rename = "@z_order", default)]
+    pub z_order: i32,
+    #[serde(rename = "attribute", default)]
+    pub attributes: Vec<CvatAttribute>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatBox {
+    #[serde(rename = "@label")]
+    pub label: String,
+    #[serde(rename = "@occluded", default)]
+    pub occluded: u8,
+    #[serde(rename = "@xtl")]
+    pub xtl: f32,
+    #[serde(rename = "@ytl")]
+    pub ytl: f32,
+    #[serde(rename = "@xbr")]
+    pub xbr: f32,
+    #[serde(rename = "@ybr")]
+    pub ybr: f32,
+    #[serde(rename = "@z_order", default)]
+    pub z_order: i32,
+    #[serde(rename = "attribute", default)]
+    pub attributes: Vec<CvatAttribute>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CvatAttribute {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "$text", default)]
+    pub value: String,
+}
+
+pub fn export(project: &Project) -> CvatAnnotations {
+    CvatAnnotations {
+        version: String::from(CVAT_VERSION),
+        meta: Some(CvatMeta {
+            task: Some(CvatTask {
+                labels: CvatLabels {
+                    labels: project
+                        .labels
+                        .iter()
+                        .map(|label| CvatLabel {
+                            name: label.name.clone(),
+                        })
+                        .collect(),
+                },
+            }),
+        }),
+        images: project
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| CvatImage {
+                id: index as u32,
+                name: file_name(&image.path),
+                width: image.width,
+                height: image.height,
+                shapes: image
+                    .annotations
+                    .iter()
+                    .filter_map(|annotation| export_annotation(project, annotation))
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Writes the project to `filename`, returning the number of annotations
+/// written without their holes, which CVAT polygons cannot hold.
+pub fn save(project: &Project, filename: &str) -> Result<usize, FormatError> {
+    let mut xml = String::new();
+    let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+    serializer.indent(' ', 2);
+    export(project).serialize(serializer)?;
+
+    fs::write(filename, xml)?;
+    Ok(project
+        .images
+        .iter()
+        .flat_map(|image| &image.annotations)
+        .filter(|annotation| annotation.polygon.len() >= 3 && !annotation.holes.is_empty())
+        .count())
+}
+
+pub fn load(filename: &str) -> Result<CvatAnnotations, FormatError> {
+    let xml = fs::read_to_string(filename)?;
+    Ok(quick_xml::de::from_str(&xml)?)
+}
+
+/// Outcome of a CVAT import, every shape of the file is counted in one of
+/// the three.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CvatImport {
+    pub imported: usize,
+    /// `<points>` shapes, which mark keypoints rather than an area and so
+    /// have no annotation to become.
+    pub points: usize,
+    /// Polylines, tracks and the other shape kinds, and degenerate polygons.
+    pub skipped: usize,
+}
+
+/// Adds the CVAT images the project does not hold yet from `directory`, where
+/// the file they name must exist, together with the LabelMe file next to each
+/// of them. CVAT images are matched to the project images by file name.
+/// Nothing is added when a file is missing.
+pub fn open_images(
+    project: &mut Project,
+    cvat: &CvatAnnotations,
+    directory: &Path,
+) -> Result<(), FormatError> {
+    let unknown: Vec<_> = cvat
+        .images
+        .iter()
+        .filter(|cvat_image| find_image(project, cvat_image).is_none())
+        .collect();
+    let missing: Vec<_> = unknown
+        .iter()
+        .map(|cvat_image| directory.join(&cvat_image.name))
+        .filter(|path| !path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    if !missing.is_empty() {
+        return Err(FormatError {
+            error_msg: format!("CVAT images not found: {}", missing.join(", ")),
+        });
+    }
+
+    for cvat_image in unknown {
+        if find_image(project, cvat_image).is_some() {
+            continue;
+        }
+        let image = project.add_image(
+            &directory.join(&cvat_image.name).to_string_lossy(),
+            cvat_image.width,
+            cvat_image.height,
+        );
+        // The LabelMe file is rewritten from the project on save, so whatever
+        // it held has to be in the project before the CVAT shapes join it.
+        labelme::load_image(project, image)?;
+    }
+    Ok(())
+}
+
+/// Adds polygons and boxes of the CVAT images to the project, mapping CVAT
+/// labels onto the project labels by name. Images the project does not hold
+/// yet are added first through [`open_images`].
+/// Annotations are polygons, so `<points>` are counted apart and every other
+/// shape kind is skipped.
+pub fn import(
+    project: &mut Project,
+    cvat: &CvatAnnotations,
+    directory: &Path,
+) -> Result<CvatImport, FormatError> {
+    open_images(project, cvat, directory)?;
+
+    let mut result = CvatImport::default();
+    for cvat_image in &cvat.images {
+        let Some(image) = find_image(project, cvat_image) else {
+            continue;
+        };
+
+        for shape in &cvat_image.shapes {
+            match shape {
+                CvatShape::Points(_) => result.points += 1,
+                _ => match import_shape(project, image, shape) {
+                    Some(()) => result.imported += 1,
+                    None => result.skipped += 1,
+                },
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn find_image(project: &Project, cvat_image: &CvatImage) -> Option<usize> {
+    project
+        .images
+        .iter()
+        .position(|image| file_name(&image.path) == file_name(&cvat_image.name))
+}
+
+fn import_shape(project: &mut Project, image: usize, shape: &CvatShape) -> Option<()> {
+    let (label, polygon, attributes) = match shape {
+        CvatShape::Polygon(polygon) => (
+            &polygon.label,
+            parse_points(&polygon.points),
+            &polygon.attributes,
+        ),
+        CvatShape::Box(cvat_box) => (
+            &cvat_box.label,
+            vec![
+                Vec2::new((cvat_box.xtl, cvat_box.ytl)),
+                Vec2::new((cvat_box.xbr, cvat_box.ytl)),
+                Vec2::new((cvat_box.xbr, cvat_box.ybr)),
+                Vec2::new((cvat_box.xtl, cvat_box.ybr)),
+            ],
+            &cvat_box.attributes,
+        ),
+        CvatShape::Points(_) | CvatShape::Other => return None,
+    };
+    if polygon.len() < 3 {
+        return None;
+    }
+
+    let label = project.add_label(label);
+    let id = project.create_annotation(image, label, polygon)?;
+    let annotation = project.image_mut(image)?.annotation_mut(id)?;
+    annotation.attributes = attributes
+        .iter()
+        .map(|attribute| (attribute.name.clone(), attribute.value.clone()))
+        .collect();
+    Some(())
+}
+
+fn export_annotation(project: &Project, annotation: &Annotation) -> Option<CvatShape> {
+    if annotation.polygon.len() < 3 {
+        return None;
+    }
+
+    Some(CvatShape::Polygon(CvatPolygon {
+        label: project.labels.get(annotation.label)?.name.clone(),
+        occluded: 0,
+        points: format_points(&annotation.polygon),
+        z_order: 0,
+        attributes: export_attributes(&annotation.attributes),
+    }))
+}
+
+fn export_attributes(attributes: &BTreeMap<String, String>) -> Vec<CvatAttribute> {
+    attributes
+        .iter()
+        .map(|(name, value)| CvatAttribute {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+fn format_points(points: &[Vec2]) -> String {
+    points
+        .iter()
+        .map(|point| format!("{},{}", point.x, point.y))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn parse_points(points: &str) -> Vec<Vec2> {
+    points
+        .split(';')
+        .filter_map(|point| {
+            let (x, y) = point.split_once(',')?;
+            Some(Vec2::new((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::project_with_image;
+
+    #[test]
+    fn round_trip_keeps_polygons_and_attributes() {
+        let (mut project, image) = project_with_image(640, 480);
+        project.add_image("tracer/images/asuka.jpg", 320, 240);
+        let polygon = vec![
+            Vec2::new((10.5, 20.333334)),
+            Vec2::new((50.0, 20.0)),
+            Vec2::new((30.0, 60.75)),
+        ];
+        let id = project
+            .create_annotation(image, 1, polygon.clone())
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().attributes =
+            BTreeMap::from([(String::from("color"), String::from("red"))]);
+
+        let xml = quick_xml::se::to_string(&export(&project)).unwrap();
+        let parsed: CvatAnnotations = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(parsed.images.len(), 2);
+
+        let images = Path::new(env!("CARGO_MANIFEST_DIR")).join("images");
+        let mut restored = Project::new(&["gmd"]);
+        restored.add_image("boomer.jpg", 640, 480);
+        assert_eq!(
+            import(&mut restored, &parsed, &images).unwrap(),
+            CvatImport {
+                imported: 1,
+                points: 0,
+                skipped: 0
+            }
+        );
+        assert_eq!(restored.images.len(), 2);
+        assert_eq!(
+            (restored.images[1].width, restored.images[1].height),
+            (320, 240)
+        );
+
+        let annotation = &restored.images[0].annotations[0];
+        assert_eq!(annotation.label, 0);
+        assert_eq!(annotation.polygon, polygon);
+        assert_eq!(annotation.attributes["color"], "red");
+    }
+
+    #[test]
+    fn reads_cvat_boxes_and_skips_points() {
+        let xml = r#"
+            <annotations>
+                <version>1.1</version>
+                <meta><task><name>vendor</name></task></meta>
+                <image id="0" name="batch/boomer.jpg" width="640" height="480">
+                    <box label="jp2" occluded="0" xtl="10" ytl="20" xbr="40" ybr="60" z_order="0">
+                        <attribute name="truncated">false</attribute>
+                    </box>
+                    <points label="jp2" occluded="0" points="1,2;3,4;5,6" z_order="0"/>
+                    <polyline label="jp2" occluded="0" points="1,2;3,4" z_order="0"/>
+                </image>
+            </annotations>
+        "#;
+        let cvat: CvatAnnotations = quick_xml::de::from_str(xml).unwrap();
+
+        let mut project = Project::new(&["jp2"]);
+        project.add_image("boomer.jpg", 640, 480);
+        assert_eq!(
+            import(&mut project, &cvat, Path::new(".")).unwrap(),
+            CvatImport {
+                imported: 1,
+                points: 1,
+                skipped: 1
+            }
+        );
+
+        let annotation = &project.images[0].annotations[0];
+        assert_eq!(annotation.area(), 1200.0);
+        assert_eq!(annotation.attributes["truncated"], "false");
+    }
+
+    #[test]
+    fn adds_the_images_of_a_task_from_the_xml_directory() {
+        let xml = r#"
+            <annotations>
+                <image id="0" name="asuka.jpg" width="640" height="480">
+                    <box label="jp2" xtl="10" ytl="20" xbr="40" ybr="60"/>
+                </image>
+                <image id="1" name="boomer.jpg" width="800" height="600">
+                    <polygon label="gmd" points="1,2;30,4;5,60"/>
+                </image>
+            </annotations>
+        "#;
+        let cvat: CvatAnnotations = quick_xml::de::from_str(xml).unwrap();
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("images");
+
+        let mut project = Project::new(&["jp2", "gmd"]);
+        assert_eq!(
+            import(&mut project, &cvat, &directory).unwrap(),
+            CvatImport {
+                imported: 2,
+                points: 0,
+                skipped: 0
+            }
+        );
+        assert_eq!(project.images.len(), 2);
+        let boomer = &project.images[1];
+        assert_eq!(
+            Path::new(&boomer.path),
+            directory.join("boomer.jpg").as_path()
+        );
+        assert_eq!((boomer.width, boomer.height), (800, 600));
+        assert_eq!(boomer.annotations[0].label, 1);
+
+        let mut project = Project::new(&["jp2", "gmd"]);
+        let missing = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert!(import(&mut project, &cvat, missing).is_err());
+        assert!(project.images.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_labelme_annotations_of_added_images() {
+        let directory = std::env::temp_dir().join(format!("tracer-cvat-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("boomer.jpg"), []).unwrap();
+        fs::write(
+            directory.join("boomer.json"),
+            r#"{
+                "shapes": [
+                    {"label": "jp2", "points": [[0, 0], [10, 0], [10, 10]], "shape_type": "polygon"}
+                ],
+                "imagePath": "boomer.jpg",
+                "imageHeight": 480,
+                "imageWidth": 640
+            }"#,
+        )
+        .unwrap();
+        let xml = r#"
+            <annotations>
+                <image id="0" name="boomer.jpg" width="640" height="480">
+                    <polygon label="gmd" points="1,2;30,4;5,60"/>
+                </image>
+            </annotations>
+        "#;
+        let cvat: CvatAnnotations = quick_xml::de::from_str(xml).unwrap();
+
+        let mut project = Project::new(&["jp2", "gmd"]);
+        let result = import(&mut project, &cvat, &directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(result.unwrap().imported, 1);
+
+        let labels: Vec<_> = project.images[0]
+            .annotations
+            .iter()
+            .map(|annotation| annotation.label)
+            .collect();
+        assert_eq!(labels, [0, 1]);
+    }
+}
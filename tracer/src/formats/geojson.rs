@@ -0,0 +1,442 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, Project},
+    intersector::Intersector,
+    matrix::Matrix,
+    vec::Vec2,
+};
+
+use super::{file_name, label_path, FormatError};
+
+const WORLD_FILE_EXTENSIONS: [&str; 5] = ["wld", "jgw", "pgw", "tfw", "bpw"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum GeoJson {
+    FeatureCollection { features: Vec<Feature> },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Feature {
+    Feature {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        geometry: Option<Geometry>,
+        #[serde(default)]
+        properties: Option<Map<String, Value>>,
+    },
+}
+
+/// Geometries annotations can be made from. Points, lines and collections
+/// are kept as `Other` and skipped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Polygon(Vec<Vec<[f64; 2]>>),
+    MultiPolygon(Vec<Vec<Vec<[f64; 2]>>>),
+    #[serde(untagged)]
+    Other(Value),
+}
+
+/// Affine pixel to world transform read from an ESRI world file. World
+/// coordinates are usually far too large for `f32`, so the transform is split
+/// into the world position of the image corner and a matrix mapping pixels to
+/// offsets from it.
+#[derive(Clone, Copy)]
+pub struct WorldFile {
+    pub origin: (f64, f64),
+    pub matrix: Matrix,
+}
+
+impl WorldFile {
+    /// Parses the six lines of a world file: pixel size in x, the two rotation
+    /// terms, pixel size in y and the world position of the center of the top
+    /// left pixel.
+    pub fn parse(text: &str) -> Result<Self, FormatError> {
+        let values = text
+            .split_whitespace()
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|values| values.len() == 6)
+            .ok_or_else(|| FormatError {
+                error_msg: String::from("World file must hold six numbers"),
+            })?;
+        let [a, d, b, e, c, f] = [
+            values[0], values[1], values[2], values[3], values[4], values[5],
+        ];
+
+        let mut matrix = Matrix::ident();
+        matrix.data[0][0] = a as f32;
+        matrix.data[0][1] = d as f32;
+        matrix.data[1][0] = b as f32;
+        matrix.data[1][1] = e as f32;
+        Ok(WorldFile {
+            origin: (c - 0.5 * (a + b), f - 0.5 * (d + e)),
+            matrix,
+        })
+    }
+
+    /// Looks for a world file next to the image, trying the generic `.wld`
+    /// extension and the common per-format ones.
+    pub fn find(image_path: &str) -> Result<Option<Self>, FormatError> {
+        let directory = Path::new(image_path).parent().unwrap_or(Path::new(""));
+        WORLD_FILE_EXTENSIONS
+            .iter()
+            .map(|extension| label_path(directory, image_path, extension))
+            .find(|path| path.exists())
+            .map(|path| Self::parse(&fs::read_to_string(path)?))
+            .transpose()
+    }
+
+    pub fn to_world(&self, pixel: Vec2) -> [f64; 2] {
+        let offset = self.matrix * pixel;
+        [
+            self.origin.0 + offset.x as f64,
+            self.origin.1 + offset.y as f64,
+        ]
+    }
+
+    pub fn to_pixel(&self, [x, y]: [f64; 2]) -> Option<Vec2> {
+        let offset = Vec2::new(((x - self.origin.0) as f32, (y - self.origin.1) as f32));
+        let data = &self.matrix.data;
+        let determinant = data[0][0] * data[1][1] - data[1][0] * data[0][1];
+        if determinant == 0.0 {
+            return None;
+        }
+        Some(Vec2::new((
+            (offset.x * data[1][1] - offset.y * data[1][0]) / determinant,
+            (offset.y * data[0][0] - offset.x * data[0][1]) / determinant,
+        )))
+    }
+}
+
+/// Builds a feature collection of the image annotations. Coordinates are world
+/// coordinates when a world file is given and pixel coordinates otherwise.
+/// Rings are closed and oriented as RFC 7946 asks, outlines counterclockwise
+/// and holes clockwise.
+pub fn export(project: &Project, image: &AnnotatedImage, world: Option<&WorldFile>) -> GeoJson {
+    GeoJson::FeatureCollection {
+        features: image
+            .annotations
+            .iter()
+            .filter_map(|annotation| export_annotation(project, image, annotation, world))
+            .collect(),
+    }
+}
+
+/// Writes a `.geojson` file per image of the project into `directory`,
+/// georeferenced by the world file next to the image if there is one.
+pub fn save(project: &Project, directory: &str) -> Result<(), FormatError> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+
+    for image in &project.images {
+        let world = WorldFile::find(&image.path)?;
+        let json = serde_json::to_string_pretty(&export(project, image, world.as_ref()))?;
+        fs::write(label_path(directory, &image.path, "geojson"), json)?;
+    }
+    Ok(())
+}
+
+pub fn load(filename: &str) -> Result<GeoJson, FormatError> {
+    let json = fs::read_to_string(filename)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Outcome of a GeoJSON import. Imported counts annotations, of which a
+/// multipolygon feature can make several, skipped counts features.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeoJsonImport {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Adds the polygon features to the given project image, mapping world
+/// coordinates back to pixels when a world file is given. Every polygon of a
+/// multipolygon becomes its own annotation. Features without a geometry or
+/// with one that is not a polygon or multipolygon are skipped.
+pub fn import(
+    project: &mut Project,
+    image: usize,
+    geojson: &GeoJson,
+    world: Option<&WorldFile>,
+) -> GeoJsonImport {
+    let GeoJson::FeatureCollection { features } = geojson;
+
+    let mut result = GeoJsonImport::default();
+    for Feature::Feature {
+        geometry,
+        properties,
+        ..
+    } in features
+    {
+        let polygons = match geometry {
+            Some(Geometry::Polygon(rings)) => vec![rings.clone()],
+            Some(Geometry::MultiPolygon(polygons)) => polygons.clone(),
+            Some(Geometry::Other(_)) | None => {
+                result.skipped += 1;
+                continue;
+            }
+        };
+        let property = |name: &str| {
+            properties
+                .as_ref()
+                .and_then(|properties| properties.get(name))
+                .and_then(|value| value.as_str())
+        };
+        let label = project.add_label(property("label").unwrap_or("unlabeled"));
+        let author = property("author").map(String::from);
+
+        for rings in polygons {
+            let mut rings = rings.iter().filter_map(|ring| import_ring(ring, world));
+            let Some(outline) = rings.next() else {
+                continue;
+            };
+            let holes: Vec<_> = rings.collect();
+            let Some(id) = project.create_annotation(image, label, outline) else {
+                continue;
+            };
+            if let Some(annotation) = project
+                .image_mut(image)
+                .and_then(|image| image.annotation_mut(id))
+            {
+                annotation.holes = holes;
+                if let Some(author) = &author {
+                    annotation.author = author.clone();
+                }
+            }
+            result.imported += 1;
+        }
+    }
+    result
+}
+
+/// Imports the `.geojson` files in `directory` belonging to the images of the
+/// project, using the world files next to the images.
+pub fn import_directory(
+    project: &mut Project,
+    directory: &str,
+) -> Result<GeoJsonImport, FormatError> {
+    let mut result = GeoJsonImport::default();
+    for image in 0..project.images.len() {
+        let path = project.images[image].path.clone();
+        let filename = label_path(Path::new(directory), &path, "geojson");
+        if filename.exists() {
+            let geojson = load(&filename.to_string_lossy())?;
+            let world = WorldFile::find(&path)?;
+            let imported = import(project, image, &geojson, world.as_ref());
+            result.imported += imported.imported;
+            result.skipped += imported.skipped;
+        }
+    }
+    Ok(result)
+}
+
+fn export_annotation(
+    project: &Project,
+    image: &AnnotatedImage,
+    annotation: &Annotation,
+    world: Option<&WorldFile>,
+) -> Option<Feature> {
+    if annotation.polygon.len() < 3 {
+        return None;
+    }
+    let label = project.labels.get(annotation.label)?;
+
+    let mut properties = Map::new();
+    properties.insert(String::from("label"), Value::from(label.name.clone()));
+    properties.insert(String::from("id"), Value::from(annotation.id));
+    properties.insert(
+        String::from("author"),
+        Value::from(annotation.author.clone()),
+    );
+    properties.insert(String::from("image"), Value::from(file_name(&image.path)));
+    annotation.attributes.iter().for_each(|(name, value)| {
+        properties
+            .entry(name.clone())
+            .or_insert_with(|| Value::from(value.clone()));
+    });
+
+    let rings = std::iter::once(export_ring(&annotation.polygon, world, true))
+        .chain(
+            annotation
+                .holes
+                .iter()
+                .map(|hole| export_ring(hole, world, false)),
+        )
+        .collect();
+
+    Some(Feature::Feature {
+        id: Some(Value::from(annotation.id)),
+        geometry: Some(Geometry::Polygon(rings)),
+        properties: Some(properties),
+    })
+}
+
+fn export_ring(points: &[Vec2], world: Option<&WorldFile>, outline: bool) -> Vec<[f64; 2]> {
+    let mut ring: Vec<[f64; 2]> = points
+        .iter()
+        .map(|point| match world {
+            Some(world) => world.to_world(*point),
+            None => [point.x as f64, point.y as f64],
+        })
+        .collect();
+
+    let area = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .fold(0.0, |area, ([x1, y1], [x2, y2])| area + x1 * y2 - x2 * y1);
+    if (area > 0.0) != outline {
+        ring.reverse();
+    }
+    ring.push(ring[0]);
+    ring
+}
+
+fn import_ring(ring: &[[f64; 2]], world: Option<&WorldFile>) -> Option<Vec<Vec2>> {
+    let ring = match ring.split_last() {
+        Some((last, rest)) if rest.first() == Some(last) => rest,
+        _ => ring,
+    };
+    let points = ring
+        .iter()
+        .map(|[x, y]| match world {
+            Some(world) => world.to_pixel([*x, *y]),
+            None => Some(Vec2::new((*x as f32, *y as f32))),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    (points.len() >= 3 && Intersector::signed_area(&points) != 0.0).then_some(points)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::{project_with_image, square};
+
+    fn project_with_ring() -> Project {
+        let (mut project, image) = project_with_image(1000, 1000);
+        project.author = String::from("annotator");
+        let id = project
+            .create_annotation(image, 1, square(100.0, 100.0, 200.0))
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().holes = vec![square(150.0, 150.0, 50.0)];
+        project
+    }
+
+    fn ring_area(ring: &[[f64; 2]]) -> f64 {
+        ring.windows(2)
+            .map(|pair| pair[0][0] * pair[1][1] - pair[1][0] * pair[0][1])
+            .sum::<f64>()
+            * 0.5
+    }
+
+    #[test]
+    fn world_file_maps_pixel_corners() {
+        let world = WorldFile::parse("0.5\n0.0\n0.0\n-0.5\n500000.25\n5400000.25\n").unwrap();
+
+        assert_eq!(world.to_world(Vec2::new((0.0, 0.0))), [500000.0, 5400000.5]);
+        assert_eq!(
+            world.to_world(Vec2::new((10.0, 4.0))),
+            [500005.0, 5400000.5 - 2.0]
+        );
+        assert_eq!(
+            world.to_pixel([500005.0, 5400000.5 - 2.0]),
+            Some(Vec2::new((10.0, 4.0)))
+        );
+        assert!(WorldFile::parse("1 0 0 -1 0").is_err());
+    }
+
+    #[test]
+    fn tiny_pixel_sizes_stay_invertible() {
+        let world = WorldFile::parse("1e-8 0 0 -1e-8 12.5 48.25").unwrap();
+        let pixel = world
+            .to_pixel(world.to_world(Vec2::new((10.0, 4.0))))
+            .unwrap();
+
+        assert!((pixel.x - 10.0).abs() < 1e-3 && (pixel.y - 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn exports_closed_rfc_oriented_rings_with_properties() {
+        let project = project_with_ring();
+        let GeoJson::FeatureCollection { features } = export(&project, &project.images[0], None);
+
+        let Feature::Feature {
+            geometry: Some(Geometry::Polygon(rings)),
+            properties: Some(properties),
+            ..
+        } = &features[0]
+        else {
+            panic!("expected a polygon feature");
+        };
+        assert_eq!(rings.len(), 2);
+        assert!(rings.iter().all(|ring| ring.first() == ring.last()));
+        assert_eq!(ring_area(&rings[0]), 40000.0);
+        assert_eq!(ring_area(&rings[1]), -2500.0);
+        assert_eq!(properties["label"], "gmd");
+        assert_eq!(properties["id"], 1);
+        assert_eq!(properties["author"], "annotator");
+    }
+
+    #[test]
+    fn georeferenced_round_trip() {
+        let project = project_with_ring();
+        let world = WorldFile::parse("0.25 0 0 -0.25 650000.125 5300000.125").unwrap();
+        let geojson = export(&project, &project.images[0], Some(&world));
+
+        let json = serde_json::to_string(&geojson).unwrap();
+        assert!(json.contains(r#""type":"FeatureCollection""#));
+        let parsed: GeoJson = serde_json::from_str(&json).unwrap();
+
+        let mut imported = Project::new(&["gmd"]);
+        imported.add_image("ortho.jpg", 1000, 1000);
+        assert_eq!(
+            import(&mut imported, 0, &parsed, Some(&world)),
+            GeoJsonImport {
+                imported: 1,
+                skipped: 0
+            }
+        );
+
+        let annotation = &imported.images[0].annotations[0];
+        assert_eq!(annotation.label, 0);
+        assert_eq!(annotation.author, "annotator");
+        assert_eq!(annotation.holes.len(), 1);
+        assert_eq!(annotation.area(), 40000.0 - 2500.0);
+    }
+
+    #[test]
+    fn skips_features_that_are_not_polygons() {
+        let json = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "geometry": null, "properties": {}},
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [1, 2]}},
+                {"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[1, 2], [3, 4]]}},
+                {"type": "Feature", "geometry": {"type": "GeometryCollection", "geometries": []}},
+                {"type": "Feature", "geometry": {"type": "MultiPolygon", "coordinates": [
+                    [[[0, 0], [10, 0], [10, 10], [0, 0]]],
+                    [[[20, 0], [30, 0], [30, 10], [20, 0]]]
+                ]}}
+            ]
+        }"#;
+        let geojson: GeoJson = serde_json::from_str(json).unwrap();
+
+        let mut project = Project::new(&["gmd"]);
+        project.add_image("ortho.jpg", 1000, 1000);
+        assert_eq!(
+            import(&mut project, 0, &geojson, None),
+            GeoJsonImport {
+                imported: 2,
+                skipped: 4
+            }
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, AnnotationId, Project},
+    triangulator::Triangulator,
+    vec::Vec2,
+};
+
+use super::{file_name, label_path, FormatError};
+
+const LABELME_VERSION: &str = "5.2.1";
+const HOLE_FLAG: &str = "hole";
+/// Key of the LabelMe data kept on an [`AnnotatedImage`].
+const RETAINED_KEY: &str = "labelme";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelMeFile {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+    pub shapes: Vec<LabelMeShape>,
+    pub image_path: String,
+    #[serde(default)]
+    pub image_data: Option<String>,
+    pub image_height: u32,
+    pub image_width: u32,
+    /// Fields LabelMe or other tools add that are not read here.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LabelMeShape {
+    pub label: String,
+    pub points: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub group_id: Option<u32>,
+    #[serde(default)]
+    pub description: String,
+    pub shape_type: String,
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// What an image keeps of its LabelMe file beyond the annotations, so that
+/// saving does not lose what the editor cannot show.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Retained {
+    flags: BTreeMap<String, bool>,
+    image_data: Option<String>,
+    extra: Map<String, Value>,
+    /// Shapes that did not become annotations, points and lines among them.
+    shapes: Vec<LabelMeShape>,
+    /// Fields of the shapes that became annotations, by annotation. Hole
+    /// shapes are written from their outline's.
+    details: BTreeMap<AnnotationId, ShapeDetails>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ShapeDetails {
+    group_id: Option<u32>,
+    description: String,
+    flags: BTreeMap<String, bool>,
+    extra: Map<String, Value>,
+}
+
+/// Builds the LabelMe document of an image. LabelMe has no notion of holes, so
+/// they are stored as extra polygons sharing the `group_id` of their outline
+/// and carrying the `hole` flag. Outlines keep the `group_id` they were loaded
+/// with, the others get one no retained shape uses. Whatever the image kept of
+/// the file it was loaded from is written back as it was.
+pub fn export(project: &Project, image: &AnnotatedImage) -> LabelMeFile {
+    let retained = retained(image);
+    let mut used: BTreeSet<u32> = retained
+        .shapes
+        .iter()
+        .filter_map(|shape| shape.group_id)
+        .chain(
+            retained
+                .details
+                .values()
+                .filter_map(|details| details.group_id),
+        )
+        .collect();
+
+    let mut shapes = vec![];
+    for annotation in &image.annotations {
+        let details = retained.details.get(&annotation.id);
+        let group_id = match details.and_then(|details| details.group_id) {
+            Some(group_id) => Some(group_id),
+            None if !annotation.holes.is_empty() => {
+                let group_id = if used.contains(&annotation.id) {
+                    used.last().map_or(0, |last| last + 1)
+                } else {
+                    annotation.id
+                };
+                used.insert(group_id);
+                Some(group_id)
+            }
+            None => None,
+        };
+        shapes.extend(export_annotation(project, annotation, details, group_id));
+    }
+    shapes.extend(retained.shapes);
+
+    LabelMeFile {
+        version: String::from(LABELME_VERSION),
+        flags: retained.flags,
+        shapes,
+        image_path: file_name(&image.path),
+        image_data: retained.image_data,
+        image_height: image.height,
+        image_width: image.width,
+        extra: retained.extra,
+    }
+}
+
+/// Path of the LabelMe file stored next to the image.
+pub fn annotation_path(image_path: &str) -> String {
+    let directory = Path::new(image_path).parent().unwrap_or(Path::new(""));
+    label_path(directory, image_path, "json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Saves every image of the project into a LabelMe file next to it.
+pub fn save(project: &Project) -> Result<(), FormatError> {
+    for image in &project.images {
+        let json = serde_json::to_string_pretty(&export(project, image))?;
+        fs::write(annotation_path(&image.path), json)?;
+    }
+    Ok(())
+}
+
+pub fn load(filename: &str) -> Result<LabelMeFile, FormatError> {
+    let json = fs::read_to_string(filename)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Adds the shapes of the document to the given project image. Polygons and
+/// rectangles become annotations, the other shapes and the fields the model
+/// has no place for are kept on the image for [`export`]. Returns the number
+/// of annotations created.
+pub fn import(project: &mut Project, image: usize, file: &LabelMeFile) -> usize {
+    let Some(mut retained) = project.image(image).map(retained) else {
+        return 0;
+    };
+    retained.flags = file.flags.clone();
+    retained.image_data = file.image_data.clone();
+    retained.extra = file.extra.clone();
+
+    let (supported, unsupported): (Vec<_>, Vec<_>) = file
+        .shapes
+        .iter()
+        .partition(|shape| shape.shape_type == "polygon" || shape.shape_type == "rectangle");
+    retained.shapes.extend(unsupported.into_iter().cloned());
+    let (holes, outlines): (Vec<_>, Vec<_>) = supported
+        .into_iter()
+        .partition(|shape| shape.flags.get(HOLE_FLAG).copied().unwrap_or(false));
+
+    let mut imported = 0;
+    let mut created = vec![];
+    for shape in outlines {
+        let polygon = import_points(shape);
+        if polygon.len() < 3 {
+            retained.shapes.push(shape.clone());
+            continue;
+        }
+        let label = project.add_label(&shape.label);
+        let Some(id) = project.create_annotation(image, label, polygon.clone()) else {
+            continue;
+        };
+        retained.details.insert(
+            id,
+            ShapeDetails {
+                group_id: shape.group_id,
+                description: shape.description.clone(),
+                flags: shape.flags.clone(),
+                extra: shape.extra.clone(),
+            },
+        );
+
+        created.push((shape.group_id, id, polygon));
+        imported += 1;
+    }
+
+    // Other shapes may share the group of an outline, a hole goes to the one
+    // around it and only falls back to the first of the group
+    for hole in holes {
+        let points = import_points(hole);
+        let group: Vec<_> = created
+            .iter()
+            .filter(|(group_id, ..)| group_id.is_some() && *group_id == hole.group_id)
+            .collect();
+        let owner = group
+            .iter()
+            .find(|(_, _, outline)| {
+                points
+                    .first()
+                    .is_some_and(|point| Triangulator::polygon_contains(outline, *point))
+            })
+            .or(group.first());
+        let annotation = owner.filter(|_| points.len() >= 3).and_then(|(_, id, _)| {
+            project
+                .image_mut(image)
+                .and_then(|image| image.annotation_mut(*id))
+        });
+        match annotation {
+            Some(annotation) => annotation.holes.push(points),
+            None => retained.shapes.push(hole.clone()),
+        }
+    }
+
+    if let (Some(image), Ok(retained)) = (project.image_mut(image), serde_json::to_value(retained))
+    {
+        image.retained.insert(String::from(RETAINED_KEY), retained);
+    }
+    imported
+}
+
+/// Loads the LabelMe file stored next to the image, if there is one.
+pub fn load_image(project: &mut Project, image: usize) -> Result<usize, FormatError> {
+    let Some(filename) = project
+        .image(image)
+        .map(|image| annotation_path(&image.path))
+    else {
+        return Ok(0);
+    };
+    if !Path::new(&filename).exists() {
+        return Ok(0);
+    }
+
+    let file = load(&filename)?;
+    Ok(import(project, image, &file))
+}
+
+fn retained(image: &AnnotatedImage) -> Retained {
+    image
+        .retained
+        .get(RETAINED_KEY)
+        .and_then(|retained| serde_json::from_value(retained.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn export_annotation(
+    project: &Project,
+    annotation: &Annotation,
+    details: Option<&ShapeDetails>,
+    group_id: Option<u32>,
+) -> Vec<LabelMeShape> {
+    let Some(label) = project.labels.get(annotation.label) else {
+        return vec![];
+    };
+    if annotation.polygon.len() < 3 {
+        return vec![];
+    }
+
+    let details = details.cloned().unwrap_or_default();
+    let shape = |points: &[Vec2], flags: BTreeMap<String, bool>| LabelMeShape {
+        label: label.name.clone(),
+        points: points.iter().map(|point| [point.x, point.y]).collect(),
+        group_id,
+        description: details.description.clone(),
+        shape_type: String::from("polygon"),
+        flags,
+        extra: details.extra.clone(),
+    };
+
+    std::iter::once(shape(&annotation.polygon, details.flags.clone()))
+        .chain(
+            annotation
+                .holes
+                .iter()
+                .map(|hole| shape(hole, BTreeMap::from([(String::from(HOLE_FLAG), true)]))),
+        )
+        .collect()
+}
+
+fn import_points(shape: &LabelMeShape) -> Vec<Vec2> {
+    match (shape.shape_type.as_str(), shape.points.as_slice()) {
+        ("rectangle", [[x1, y1], [x2, y2]]) => vec![
+            Vec2::new((*x1, *y1)),
+            Vec2::new((*x2, *y1)),
+            Vec2::new((*x2, *y2)),
+            Vec2::new((*x1, *y2)),
+        ],
+        _ => shape
+            .points
+            .iter()
+            .map(|[x, y]| Vec2::new((*x, *y)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::{project_with_image, square};
+
+    #[test]
+    fn round_trip_keeps_labels_and_holes() {
+        let (mut project, image) = project_with_image(640, 480);
+        let id = project
+            .create_annotation(image, 1, square(10.0, 10.0, 40.0))
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().holes = vec![square(20.0, 20.0, 10.0)];
+        project
+            .create_annotation(image, 0, square(100.0, 100.0, 5.0))
+            .unwrap();
+
+        let file = export(&project, &project.images[image]);
+        assert_eq!(file.image_path, "boomer.jpg");
+        assert_eq!(file.shapes.len(), 3);
+
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(json.contains("\"imageWidth\":640"));
+        let parsed: LabelMeFile = serde_json::from_str(&json).unwrap();
+
+        let (mut restored, restored_image) = project_with_image(640, 480);
+        assert_eq!(import(&mut restored, restored_image, &parsed), 2);
+
+        let annotations = &restored.images[restored_image].annotations;
+        assert_eq!(annotations[0].label, 1);
+        assert_eq!(annotations[0].polygon, square(10.0, 10.0, 40.0));
+        assert_eq!(annotations[0].holes, vec![square(20.0, 20.0, 10.0)]);
+        assert_eq!(annotations[1].label, 0);
+        assert!(annotations[1].holes.is_empty());
+    }
+
+    #[test]
+    fn reads_labelme_rectangles() {
+        let json = r#"{
+            "version": "5.2.1",
+            "flags": {},
+            "shapes": [
+                {"label": "cat", "points": [[10, 20], [30, 50]], "group_id": null, "shape_type": "rectangle", "flags": {}},
+                {"label": "cat", "points": [[1, 1]], "group_id": null, "shape_type": "point", "flags": {}}
+            ],
+            "imagePath": "boomer.jpg",
+            "imageData": null,
+            "imageHeight": 480,
+            "imageWidth": 640
+        }"#;
+        let file: LabelMeFile = serde_json::from_str(json).unwrap();
+
+        let mut project = Project::new(&[]);
+        let image = project.add_image("boomer.jpg", 640, 480);
+        assert_eq!(import(&mut project, image, &file), 1);
+        assert_eq!(project.images[image].annotations[0].area(), 600.0);
+    }
+
+    #[test]
+    fn round_trip_keeps_what_is_not_annotated() {
+        let json = r#"{
+            "version": "5.2.1",
+            "flags": {"reviewed": true},
+            "shapes": [
+                {"label": "cat", "points": [[0, 0], [40, 0], [40, 30]], "group_id": 7, "description": "front", "shape_type": "polygon", "flags": {"occluded": true}, "mask": null},
+                {"label": "eye", "points": [[1, 1]], "group_id": null, "shape_type": "point", "flags": {}},
+                {"label": "tail", "points": [[1, 1], [5, 5]], "group_id": null, "shape_type": "line", "flags": {}},
+                {"label": "tail", "points": [[1, 1], [5, 5], [9, 1]], "group_id": null, "description": "curled", "shape_type": "linestrip", "flags": {}},
+                {"label": "ball", "points": [[20, 20], [25, 20]], "group_id": null, "shape_type": "circle", "flags": {}}
+            ],
+            "imagePath": "boomer.jpg",
+            "imageData": "aGVsbG8=",
+            "imageHeight": 480,
+            "imageWidth": 640,
+            "reviewer": "kb"
+        }"#;
+        let file: LabelMeFile = serde_json::from_str(json).unwrap();
+
+        let mut project = Project::new(&[]);
+        let image = project.add_image("boomer.jpg", 640, 480);
+        assert_eq!(import(&mut project, image, &file), 1);
+
+        let json = serde_json::to_string(&export(&project, &project.images[image])).unwrap();
+        let saved: LabelMeFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(saved.flags, file.flags);
+        assert_eq!(saved.image_data.as_deref(), Some("aGVsbG8="));
+        assert_eq!(saved.extra["reviewer"], "kb");
+        assert_eq!(saved.shapes[1..], file.shapes[1..]);
+
+        let polygon = &saved.shapes[0];
+        assert_eq!(polygon.group_id, Some(7));
+        assert_eq!(polygon.description, "front");
+        assert_eq!(polygon.flags, file.shapes[0].flags);
+        assert_eq!(polygon.extra, file.shapes[0].extra);
+    }
+
+    #[test]
+    fn round_trip_keeps_groups_shared_with_other_shapes() {
+        let json = r#"{
+            "shapes": [
+                {"label": "cat", "points": [[0, 0], [40, 0], [40, 40], [0, 40]], "group_id": 7, "shape_type": "polygon"},
+                {"label": "cat", "points": [[10, 10], [20, 10], [20, 20], [10, 20]], "group_id": 7, "shape_type": "polygon", "flags": {"hole": true}},
+                {"label": "cat", "points": [[100, 0], [140, 0], [140, 40]], "group_id": 7, "shape_type": "polygon"},
+                {"label": "eye", "points": [[1, 1]], "group_id": 3, "shape_type": "point"}
+            ],
+            "imagePath": "boomer.jpg",
+            "imageHeight": 480,
+            "imageWidth": 640
+        }"#;
+        let file: LabelMeFile = serde_json::from_str(json).unwrap();
+
+        let mut project = Project::new(&[]);
+        let image = project.add_image("boomer.jpg", 640, 480);
+        assert_eq!(import(&mut project, image, &file), 2);
+        let id = project
+            .create_annotation(image, 0, square(200.0, 0.0, 40.0))
+            .unwrap();
+        assert_eq!(id, 3);
+        project.images[image].annotation_mut(id).unwrap().holes = vec![square(210.0, 10.0, 5.0)];
+
+        let saved = export(&project, &project.images[image]);
+        let group_ids: Vec<_> = saved.shapes.iter().map(|shape| shape.group_id).collect();
+        assert_eq!(
+            group_ids,
+            [Some(7), Some(7), Some(7), Some(8), Some(8), Some(3)]
+        );
+
+        let mut restored = Project::new(&[]);
+        let restored_image = restored.add_image("boomer.jpg", 640, 480);
+        assert_eq!(import(&mut restored, restored_image, &saved), 3);
+        let holes: Vec<_> = restored.images[restored_image]
+            .annotations
+            .iter()
+            .map(|annotation| annotation.holes.len())
+            .collect();
+        assert_eq!(holes, [1, 0, 1]);
+    }
+
+    #[test]
+    fn annotation_file_sits_next_to_the_image() {
+        assert_eq!(
+            annotation_path("tracer/images/boomer.jpg"),
+            "tracer/images/boomer.json"
+        );
+        assert_eq!(annotation_path("boomer.jpg"), "boomer.json");
+    }
+}
@@ -0,0 +1,384 @@
+use std::{fs, path::Path};
+
+use image::{DynamicImage, ImageBuffer, Luma};
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, LabelClass, Project},
+    triangulator::Triangulator,
+    vec::Vec2,
+};
+
+use self::contour::{simplify, trace};
+
+use super::{label_path, FormatError};
+
+pub mod contour;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskKind {
+    /// Pixels hold the label index plus one, zero is background.
+    Class,
+    /// Pixels hold the 1-based position of the annotation in the image.
+    Instance,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskDepth {
+    Eight,
+    Sixteen,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u16>,
+}
+
+impl Mask {
+    pub fn new(width: u32, height: u32) -> Self {
+        Mask {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u16 {
+        self.data[self.index(x, y)]
+    }
+
+    /// Position of the pixel in `data`, computed in `usize` because the pixel
+    /// count of a large image does not fit in `u32`.
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Rasterizes all annotations of the image at its original resolution.
+    /// Annotations are drawn in the order they are stored, so later ones win
+    /// wherever selections overlap.
+    pub fn rasterize(image: &AnnotatedImage, kind: MaskKind) -> Self {
+        let mut mask = Mask::new(image.width, image.height);
+        image
+            .annotations
+            .iter()
+            .enumerate()
+            .for_each(|(index, annotation)| {
+                let value = match kind {
+                    MaskKind::Class => annotation.label + 1,
+                    MaskKind::Instance => index + 1,
+                };
+                mask.fill_annotation(annotation, value.min(u16::MAX as usize) as u16);
+            });
+        mask
+    }
+
+    pub fn fill_annotation(&mut self, annotation: &Annotation, value: u16) {
+        let mut triangulator = Triangulator::from_outline(&annotation.polygon, &annotation.holes);
+        if let Some((vertices, indices)) = triangulator.triangulate() {
+            indices.iter().for_each(|[a, b, c]| {
+                self.fill_triangle(
+                    [
+                        vertices[*a as usize],
+                        vertices[*b as usize],
+                        vertices[*c as usize],
+                    ],
+                    value,
+                )
+            });
+        }
+    }
+
+    /// Sets every pixel whose center lies inside the triangle or on its
+    /// boundary.
+    pub fn fill_triangle(&mut self, [a, b, c]: [Vec2; 3], value: u16) {
+        let area = (b - a).cross(c - a);
+        if area == 0.0 {
+            return;
+        }
+        let (b, c) = if area < 0.0 { (c, b) } else { (b, c) };
+
+        let min_x = a.x.min(b.x).min(c.x) - 0.5;
+        let max_x = a.x.max(b.x).max(c.x) - 0.5;
+        let min_y = a.y.min(b.y).min(c.y) - 0.5;
+        let max_y = a.y.max(b.y).max(c.y) - 0.5;
+        let (min_x, max_x) = Self::clamp_range(min_x, max_x, self.width);
+        let (min_y, max_y) = Self::clamp_range(min_y, max_y, self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let center = Vec2::new((x as f32 + 0.5, y as f32 + 0.5));
+                if (b - a).cross(center - a) >= 0.0
+                    && (c - b).cross(center - b) >= 0.0
+                    && (a - c).cross(center - c) >= 0.0
+                {
+                    let index = self.index(x, y);
+                    self.data[index] = value;
+                }
+            }
+        }
+    }
+
+    fn clamp_range(min: f32, max: f32, size: u32) -> (u32, u32) {
+        (
+            min.ceil().clamp(0.0, size as f32) as u32,
+            (max.floor() + 1.0).clamp(0.0, size as f32) as u32,
+        )
+    }
+
+    pub fn save(&self, filename: &Path, depth: MaskDepth) -> Result<(), FormatError> {
+        match depth {
+            MaskDepth::Eight => {
+                let data = self
+                    .data
+                    .iter()
+                    .map(|value| u8::try_from(*value))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| FormatError {
+                        error_msg: format!("Mask values of '{}' exceed 8 bits", filename.display()),
+                    })?;
+                Self::buffer::<u8>(self.width, self.height, data)?.save(filename)?;
+            }
+            MaskDepth::Sixteen => {
+                Self::buffer::<u16>(self.width, self.height, self.data.clone())?.save(filename)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a single channel mask PNG. 8-bit values are widened as they are
+    /// rather than rescaled to 16 bits.
+    pub fn load(filename: &Path) -> Result<Self, FormatError> {
+        let (width, height, data) = match image::open(filename)? {
+            DynamicImage::ImageLuma16(image) => (image.width(), image.height(), image.into_raw()),
+            image => {
+                let image = image.into_luma8();
+                let data = image.as_raw().iter().map(|value| *value as u16).collect();
+                (image.width(), image.height(), data)
+            }
+        };
+        Ok(Mask {
+            width,
+            height,
+            data,
+        })
+    }
+
+    fn buffer<T: image::Primitive>(
+        width: u32,
+        height: u32,
+        data: Vec<T>,
+    ) -> Result<ImageBuffer<Luma<T>, Vec<T>>, FormatError> {
+        ImageBuffer::from_raw(width, height, data).ok_or_else(|| FormatError {
+            error_msg: String::from("Mask size does not match its data"),
+        })
+    }
+}
+
+/// Writes a mask PNG per image of the project into `directory`, named after
+/// the image file.
+pub fn save(
+    project: &Project,
+    directory: &str,
+    kind: MaskKind,
+    depth: MaskDepth,
+) -> Result<(), FormatError> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+
+    for image in &project.images {
+        Mask::rasterize(image, kind).save(&label_path(directory, &image.path, "png"), depth)?;
+    }
+    Ok(())
+}
+
+/// Turns each region of a class mask into an annotation of the given project
+/// image, simplifying outlines and holes with `tolerance` in pixels. Returns
+/// the number of annotations created.
+pub fn import(project: &mut Project, image: usize, mask: &Mask, tolerance: f32) -> usize {
+    let mut imported = 0;
+    for contour in trace(mask) {
+        let label = class_label(project, contour.value);
+        let Some(id) =
+            project.create_annotation(image, label, simplify(&contour.outline, tolerance))
+        else {
+            continue;
+        };
+        if let Some(annotation) = project
+            .image_mut(image)
+            .and_then(|image| image.annotation_mut(id))
+        {
+            annotation.holes = contour
+                .holes
+                .iter()
+                .map(|hole| simplify(hole, tolerance))
+                .collect();
+        }
+        imported += 1;
+    }
+    imported
+}
+
+/// Label a class mask value stands for, the one at index `value - 1`. Past the
+/// end of the label list a label named after the value is reused, or else the
+/// list is filled up to the value with labels named after theirs, so the mask
+/// exports back unchanged.
+fn class_label(project: &mut Project, value: u16) -> usize {
+    let index = value as usize - 1;
+    if index < project.labels.len() {
+        return index;
+    }
+    if let Some(label) = project.find_label(&value.to_string()) {
+        return label;
+    }
+    while project.labels.len() <= index {
+        let position = project.labels.len();
+        project
+            .labels
+            .push(LabelClass::new(&(position + 1).to_string(), position));
+    }
+    index
+}
+
+/// Imports the class masks in `directory` belonging to the images of the
+/// project, images without one are left untouched.
+pub fn import_directory(
+    project: &mut Project,
+    directory: &str,
+    tolerance: f32,
+) -> Result<usize, FormatError> {
+    let mut imported = 0;
+    for image in 0..project.images.len() {
+        let filename = label_path(Path::new(directory), &project.images[image].path, "png");
+        if filename.exists() {
+            let mask = Mask::load(&filename)?;
+            imported += import(project, image, &mask, tolerance);
+        }
+    }
+    Ok(imported)
+}
+
+/// Masks shared by the tests of the mask and COCO RLE formats.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::Mask;
+
+    /// A mask with one row per string, each digit the value of a pixel.
+    pub(crate) fn mask_from_rows(rows: &[&str]) -> Mask {
+        let mut mask = Mask::new(rows[0].len() as u32, rows.len() as u32);
+        mask.data = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|pixel| pixel.to_digit(10).unwrap() as u16))
+            .collect();
+        mask
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::{project_with_image, rectangle};
+
+    fn count(mask: &Mask, value: u16) -> usize {
+        mask.data.iter().filter(|pixel| **pixel == value).count()
+    }
+
+    #[test]
+    fn rectangles_cover_exact_pixel_count() {
+        let (mut project, image) = project_with_image(32, 16);
+        project.create_annotation(image, 1, rectangle(2.0, 3.0, 10.0, 5.0));
+
+        let mask = Mask::rasterize(&project.images[image], MaskKind::Class);
+        assert_eq!(count(&mask, 2), 50);
+        assert_eq!(mask.get(2, 3), 2);
+        assert_eq!(mask.get(12, 3), 0);
+        assert_eq!(mask.get(11, 7), 2);
+    }
+
+    #[test]
+    fn later_annotations_win_overlaps() {
+        let (mut project, image) = project_with_image(20, 20);
+        project.create_annotation(image, 1, rectangle(0.0, 0.0, 10.0, 10.0));
+        project.create_annotation(image, 0, rectangle(5.0, 5.0, 10.0, 10.0));
+
+        let class = Mask::rasterize(&project.images[image], MaskKind::Class);
+        assert_eq!(count(&class, 2), 75);
+        assert_eq!(count(&class, 1), 100);
+
+        let instance = Mask::rasterize(&project.images[image], MaskKind::Instance);
+        assert_eq!(instance.get(7, 7), 2);
+        assert_eq!(instance.get(2, 2), 1);
+    }
+
+    #[test]
+    fn holes_stay_background() {
+        let (mut project, image) = project_with_image(20, 20);
+        let id = project
+            .create_annotation(image, 0, rectangle(0.0, 0.0, 10.0, 10.0))
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().holes =
+            vec![rectangle(2.0, 2.0, 4.0, 4.0)];
+
+        let mask = Mask::rasterize(&project.images[image], MaskKind::Class);
+        assert_eq!(count(&mask, 1), 84);
+        assert_eq!(mask.get(3, 3), 0);
+    }
+
+    #[test]
+    fn rasterized_masks_import_back_as_polygons() {
+        let (mut project, image) = project_with_image(20, 20);
+        let id = project
+            .create_annotation(image, 1, rectangle(1.0, 1.0, 12.0, 10.0))
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().holes =
+            vec![rectangle(3.0, 3.0, 4.0, 4.0)];
+        project.create_annotation(image, 0, rectangle(15.0, 2.0, 3.0, 3.0));
+        let mask = Mask::rasterize(&project.images[image], MaskKind::Class);
+
+        let (mut imported, imported_image) = project_with_image(20, 20);
+        assert_eq!(import(&mut imported, imported_image, &mask, 0.5), 2);
+
+        let annotations = &imported.images[imported_image].annotations;
+        assert_eq!(annotations[0].label, 1);
+        assert_eq!(annotations[0].area(), 120.0 - 16.0);
+        assert_eq!(annotations[0].holes.len(), 1);
+        assert_eq!(annotations[1].label, 0);
+        assert_eq!(annotations[1].area(), 9.0);
+        assert_eq!(
+            Mask::rasterize(&imported.images[imported_image], MaskKind::Class),
+            mask
+        );
+    }
+
+    #[test]
+    fn unknown_mask_values_keep_their_label_index() {
+        let mut mask = Mask::new(4, 4);
+        mask.data[5] = 7;
+
+        let mut project = Project::new(&["jp2"]);
+        let image = project.add_image("boomer.jpg", 4, 4);
+        assert_eq!(import(&mut project, image, &mask, 1.0), 1);
+        assert_eq!(project.find_label("7"), Some(6));
+        assert_eq!(project.labels.len(), 7);
+        assert_eq!(project.images[image].annotations[0].label, 6);
+        assert_eq!(
+            Mask::rasterize(&project.images[image], MaskKind::Class),
+            mask
+        );
+
+        mask.data[5] = 3;
+        project.images[image].annotations.clear();
+        import(&mut project, image, &mask, 1.0);
+        assert_eq!(project.labels.len(), 7);
+        assert_eq!(project.images[image].annotations[0].label, 2);
+    }
+
+    #[test]
+    fn shapes_outside_the_image_are_clipped() {
+        let (mut project, image) = project_with_image(8, 8);
+        project.create_annotation(image, 0, rectangle(-4.0, -4.0, 8.0, 20.0));
+
+        let mask = Mask::rasterize(&project.images[image], MaskKind::Class);
+        assert_eq!(count(&mask, 1), 32);
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use crate::{intersector::Intersector, vec::Vec2};
+
+use super::Mask;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    pub value: u16,
+    pub outline: Vec<Vec2>,
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+type Corner = (i32, i32);
+
+/// Traces every 4-connected region of nonzero pixels into its outer boundary
+/// and the boundaries of the holes inside it. Boundaries run along pixel
+/// edges, so a region covering the pixels `[x0, x1) x [y0, y1)` yields exactly
+/// that rectangle. Regions are returned in scanline order of their first
+/// pixel.
+pub fn trace(mask: &Mask) -> Vec<Contour> {
+    let (width, height) = (mask.width as i32, mask.height as i32);
+    let mut components = vec![usize::MAX; mask.data.len()];
+    let mut contours = vec![];
+
+    for start in 0..mask.data.len() {
+        let value = mask.data[start];
+        if value == 0 || components[start] != usize::MAX {
+            continue;
+        }
+
+        let component = contours.len();
+        let mut pixels = vec![start];
+        let mut stack = vec![start];
+        components[start] = component;
+        while let Some(pixel) = stack.pop() {
+            let (x, y) = pixel_position(pixel, mask.width);
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let neighbour = mask.index(nx as u32, ny as u32);
+                if mask.data[neighbour] == value && components[neighbour] == usize::MAX {
+                    components[neighbour] = component;
+                    pixels.push(neighbour);
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        let inside = |x: i32, y: i32| {
+            x >= 0
+                && y >= 0
+                && x < width
+                && y < height
+                && components[mask.index(x as u32, y as u32)] == component
+        };
+        let loops = boundary_loops(&pixels, mask.width, inside);
+
+        let (outlines, holes): (Vec<_>, Vec<_>) = loops
+            .into_iter()
+            .partition(|boundary| Intersector::signed_area(boundary) > 0.0);
+        contours.push(Contour {
+            value,
+            outline: outlines.into_iter().next().unwrap_or_default(),
+            holes,
+        });
+    }
+    contours
+}
+
+/// Collects the pixel edges separating the region from its surroundings,
+/// directed so the region lies to their right in image space, and chains them
+/// into closed loops. Where two region pixels touch only diagonally the walk
+/// turns right to stay on the current pixel, keeping them apart.
+fn boundary_loops(
+    pixels: &[usize],
+    width: u32,
+    inside: impl Fn(i32, i32) -> bool,
+) -> Vec<Vec<Vec2>> {
+    let mut edges: HashMap<Corner, Vec<Corner>> = HashMap::new();
+    let mut add_edge = |from: Corner, to: Corner| edges.entry(from).or_default().push(to);
+
+    for pixel in pixels {
+        let (x, y) = pixel_position(*pixel, width);
+        if !inside(x, y - 1) {
+            add_edge((x, y), (x + 1, y));
+        }
+        if !inside(x + 1, y) {
+            add_edge((x + 1, y), (x + 1, y + 1));
+        }
+        if !inside(x, y + 1) {
+            add_edge((x + 1, y + 1), (x, y + 1));
+        }
+        if !inside(x - 1, y) {
+            add_edge((x, y + 1), (x, y));
+        }
+    }
+
+    let mut starts: Vec<Corner> = edges.keys().copied().collect();
+    starts.sort_by_key(|(x, y)| (*y, *x));
+
+    let mut loops = vec![];
+    for start in starts {
+        while let Some(first) = edges.get_mut(&start).and_then(|targets| targets.pop()) {
+            let mut corners = vec![start];
+            let (mut previous, mut current) = (start, first);
+            while current != start {
+                corners.push(current);
+                let direction = (current.0 - previous.0, current.1 - previous.1);
+                let Some(targets) = edges.get_mut(&current) else {
+                    break;
+                };
+                let turns = [
+                    (-direction.1, direction.0),
+                    direction,
+                    (direction.1, -direction.0),
+                ];
+                let Some(index) = turns.iter().find_map(|turn| {
+                    targets
+                        .iter()
+                        .position(|target| *target == (current.0 + turn.0, current.1 + turn.1))
+                }) else {
+                    break;
+                };
+                previous = current;
+                current = targets.swap_remove(index);
+            }
+            loops.push(remove_collinear(&corners));
+        }
+    }
+    loops
+}
+
+fn pixel_position(pixel: usize, width: u32) -> Corner {
+    let width = width as usize;
+    ((pixel % width) as i32, (pixel / width) as i32)
+}
+
+fn remove_collinear(corners: &[Corner]) -> Vec<Vec2> {
+    let n = corners.len();
+    (0..n)
+        .filter(|i| {
+            let (previous, current, next) =
+                (corners[(i + n - 1) % n], corners[*i], corners[(i + 1) % n]);
+            (current.0 - previous.0) * (next.1 - current.1)
+                != (current.1 - previous.1) * (next.0 - current.0)
+        })
+        .map(|i| Vec2::new((corners[i].0 as f32, corners[i].1 as f32)))
+        .collect()
+}
+
+/// Douglas-Peucker simplification of a closed loop. The loop is split at its
+/// first point and the point farthest from it, and both chains are simplified
+/// so no dropped point lies farther than `tolerance` from the result. Loops
+/// that would collapse below a triangle are returned unchanged.
+pub fn simplify(points: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    if points.len() <= 3 {
+        return points.to_vec();
+    }
+
+    let first = points[0];
+    let farthest = (1..points.len())
+        .max_by(|a, b| {
+            let (a, b) = (points[*a] - first, points[*b] - first);
+            (a * a).total_cmp(&(b * b))
+        })
+        .unwrap_or(1);
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[farthest] = true;
+    simplify_chain(points, 0, farthest, tolerance, &mut keep);
+    simplify_chain(points, farthest, points.len(), tolerance, &mut keep);
+
+    let simplified: Vec<Vec2> = points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect();
+    if simplified.len() < 3 {
+        points.to_vec()
+    } else {
+        simplified
+    }
+}
+
+/// Marks the points kept between `start` and `end`, where `end` may be one past
+/// the last point to close the loop back to the first one.
+fn simplify_chain(points: &[Vec2], start: usize, end: usize, tolerance: f32, keep: &mut [bool]) {
+    if end <= start + 1 {
+        return;
+    }
+    let (a, b) = (points[start], points[end % points.len()]);
+
+    let (index, distance) = (start + 1..end)
+        .map(|index| (index, distance_to_segment(points[index], a, b)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap_or((start, 0.0));
+
+    if distance > tolerance {
+        keep[index] = true;
+        simplify_chain(points, start, index, tolerance, keep);
+        simplify_chain(points, index, end, tolerance, keep);
+    }
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let segment = b - a;
+    let length = segment * segment;
+    if length == 0.0 {
+        let offset = point - a;
+        return (offset * offset).sqrt();
+    }
+    let t = ((point - a) * segment / length).clamp(0.0, 1.0);
+    let offset = point - (a + Vec2::new((segment.x * t, segment.y * t)));
+    (offset * offset).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::formats::mask::test_support::mask_from_rows;
+
+    #[test]
+    fn rectangle_traces_to_four_corners() {
+        let mask = mask_from_rows(&["0000", "0110", "0110", "0000"]);
+        let contours = trace(&mask);
+
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].value, 1);
+        assert_eq!(
+            contours[0].outline,
+            vec![
+                Vec2::new((1.0, 1.0)),
+                Vec2::new((3.0, 1.0)),
+                Vec2::new((3.0, 3.0)),
+                Vec2::new((1.0, 3.0)),
+            ]
+        );
+        assert!(contours[0].holes.is_empty());
+    }
+
+    #[test]
+    fn ring_traces_outline_and_hole() {
+        let mask = mask_from_rows(&["22222", "20002", "20102", "20002", "22222"]);
+        let contours = trace(&mask);
+
+        assert_eq!(contours.len(), 2);
+        assert_eq!(contours[0].value, 2);
+        assert_eq!(Intersector::signed_area(&contours[0].outline), 25.0);
+        assert_eq!(contours[0].holes.len(), 1);
+        assert_eq!(Intersector::signed_area(&contours[0].holes[0]), -9.0);
+        assert_eq!(contours[1].value, 1);
+        assert_eq!(Intersector::signed_area(&contours[1].outline), 1.0);
+    }
+
+    #[test]
+    fn diagonal_pixels_are_separate_regions() {
+        let mask = mask_from_rows(&["10", "01"]);
+        let contours = trace(&mask);
+
+        assert_eq!(contours.len(), 2);
+        assert!(contours.iter().all(|contour| contour.outline.len() == 4));
+    }
+
+    #[test]
+    fn diagonal_touch_inside_a_region_keeps_one_outline() {
+        let mask = mask_from_rows(&["1111", "1101", "1011", "1111"]);
+        let contours = trace(&mask);
+
+        assert_eq!(contours.len(), 1);
+        assert_eq!(Intersector::signed_area(&contours[0].outline), 16.0);
+        assert_eq!(contours[0].holes.len(), 1);
+        assert_eq!(Intersector::signed_area(&contours[0].holes[0]), -2.0);
+    }
+
+    #[test]
+    fn simplification_respects_tolerance() {
+        let staircase: Vec<Vec2> = (0..10)
+            .flat_map(|i| {
+                let i = i as f32;
+                [Vec2::new((i, i)), Vec2::new((i + 1.0, i))]
+            })
+            .chain([Vec2::new((10.0, 10.0)), Vec2::new((0.0, 10.0))])
+            .collect();
+
+        let simplified = simplify(&staircase, 1.0);
+        assert!(simplified.len() < staircase.len());
+        assert!(simplified.len() >= 3);
+        assert!(staircase.iter().all(|point| {
+            (0..simplified.len()).any(|i| {
+                let next = simplified[(i + 1) % simplified.len()];
+                distance_to_segment(*point, simplified[i], next) <= 1.0
+            })
+        }));
+
+        assert_eq!(simplify(&staircase, 0.0).len(), staircase.len());
+    }
+}
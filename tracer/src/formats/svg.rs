@@ -0,0 +1,272 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use base64::Engine;
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, Project},
+    vec::Vec2,
+};
+
+use super::{label_path, FormatError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SvgImage {
+    /// References the image file by its path relative to the SVG.
+    Linked,
+    /// Inlines the image file as a base64 data URI.
+    Embedded,
+}
+
+/// Renders the image and its annotations as an SVG in pixel coordinates. Each
+/// annotation becomes a `<path>` filled with its label colour, holes cut out by
+/// the even-odd rule, followed by the label name above its bounding box.
+/// `directory` is where the SVG goes, which linked images are relative to.
+pub fn export(
+    project: &Project,
+    image: &AnnotatedImage,
+    mode: SvgImage,
+    directory: &Path,
+) -> Result<String, FormatError> {
+    let href = match mode {
+        SvgImage::Linked => relative_href(directory, Path::new(&image.path)),
+        SvgImage::Embedded => format!(
+            "data:{};base64,{}",
+            mime_type(&image.path),
+            base64::engine::general_purpose::STANDARD.encode(fs::read(&image.path)?)
+        ),
+    };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        image.width, image.height
+    );
+    let _ = writeln!(
+        svg,
+        r#"  <image href="{}" x="0" y="0" width="{}" height="{}"/>"#,
+        escape(&href),
+        image.width,
+        image.height
+    );
+    image
+        .annotations
+        .iter()
+        .filter_map(|annotation| export_annotation(project, annotation))
+        .for_each(|element| svg.push_str(&element));
+    svg.push_str("</svg>\n");
+
+    Ok(svg)
+}
+
+/// Writes an SVG per image of the project into `directory`, named after the
+/// image file.
+pub fn save(project: &Project, directory: &str, mode: SvgImage) -> Result<(), FormatError> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+
+    for image in &project.images {
+        fs::write(
+            label_path(directory, &image.path, "svg"),
+            export(project, image, mode, directory)?,
+        )?;
+    }
+    Ok(())
+}
+
+fn export_annotation(project: &Project, annotation: &Annotation) -> Option<String> {
+    if annotation.polygon.len() < 3 {
+        return None;
+    }
+    let label = project.labels.get(annotation.label)?;
+    let (min, _) = annotation.bounding_box()?;
+    let [r, g, b] = label.color;
+    let color = format!("#{:02x}{:02x}{:02x}", r, g, b);
+
+    let path = std::iter::once(&annotation.polygon)
+        .chain(&annotation.holes)
+        .map(|ring| path_data(ring))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut element = String::new();
+    let _ = writeln!(element, r#"  <g id="annotation-{}">"#, annotation.id);
+    let _ = writeln!(
+        element,
+        r#"    <path d="{}" fill="{}" fill-opacity="0.5" fill-rule="evenodd" stroke="{}"/>"#,
+        path, color, color
+    );
+    let _ = writeln!(
+        element,
+        r#"    <text x="{}" y="{}" fill="{}" font-family="sans-serif" font-size="14">{}</text>"#,
+        min.x,
+        (min.y - 4.0).max(14.0),
+        color,
+        escape(&label.name)
+    );
+    element.push_str("  </g>\n");
+    Some(element)
+}
+
+fn path_data(points: &[Vec2]) -> String {
+    points
+        .iter()
+        .enumerate()
+        .fold(String::new(), |mut path, (index, point)| {
+            let command = if index == 0 { "M" } else { " L" };
+            let _ = write!(path, "{} {} {}", command, point.x, point.y);
+            path
+        })
+        + " Z"
+}
+
+/// Percent-encoded URL of `target` relative to `directory`, so that the
+/// SVG keeps working when moved along with the images.
+fn relative_href(directory: &Path, target: &Path) -> String {
+    let (directory, target) = (absolute(directory), absolute(target));
+    let common = directory
+        .components()
+        .zip(target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let parents = directory
+        .components()
+        .skip(common)
+        .map(|_| String::from(".."));
+    let rest = target
+        .components()
+        .skip(common)
+        .map(|component| percent_encode(&component.as_os_str().to_string_lossy()));
+    parents.chain(rest).collect::<Vec<_>>().join("/")
+}
+
+/// Joins relative paths onto the working directory and resolves `.` and `..`
+/// without touching the file system, the image may not exist.
+fn absolute(path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .unwrap_or_default()
+        .join(path)
+        .components()
+        .fold(PathBuf::new(), |mut absolute, component| {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    absolute.pop();
+                }
+                component => absolute.push(component),
+            }
+            absolute
+        })
+}
+
+fn percent_encode(segment: &str) -> String {
+    segment.bytes().fold(String::new(), |mut encoded, byte| {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+        encoded
+    })
+}
+
+fn mime_type(path: &str) -> &'static str {
+    match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("bmp") => "image/bmp",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+        escaped
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::square;
+
+    #[test]
+    fn paths_use_pixel_coordinates_and_label_colors() {
+        let mut project = Project::new(&["jp2", "<gmd>"]);
+        let image = project.add_image("missing/boomer.jpg", 640, 480);
+        let id = project
+            .create_annotation(image, 1, square(10.5, 20.0, 40.0))
+            .unwrap();
+        project.images[image].annotation_mut(id).unwrap().holes = vec![square(20.0, 30.0, 5.0)];
+
+        let svg = export(
+            &project,
+            &project.images[image],
+            SvgImage::Linked,
+            Path::new("."),
+        )
+        .unwrap();
+        let [r, g, b] = project.labels[1].color;
+        let color = format!("#{:02x}{:02x}{:02x}", r, g, b);
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"viewBox="0 0 640 480""#));
+        assert!(svg.contains(r#"href="missing/boomer.jpg""#));
+        assert!(svg.contains(
+            r#"d="M 10.5 20 L 50.5 20 L 50.5 60 L 10.5 60 Z M 20 30 L 25 30 L 25 35 L 20 35 Z""#
+        ));
+        assert!(svg.contains(&format!(r#"fill="{}""#, color)));
+        assert!(svg.contains(">&lt;gmd&gt;</text>"));
+    }
+
+    #[test]
+    fn embedding_a_missing_image_fails() {
+        let mut project = Project::new(&["jp2"]);
+        let image = project.add_image("missing/boomer.jpg", 640, 480);
+        assert!(export(
+            &project,
+            &project.images[image],
+            SvgImage::Embedded,
+            Path::new(".")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn links_are_relative_and_percent_encoded() {
+        assert_eq!(
+            relative_href(
+                Path::new("tracer/svg"),
+                Path::new("tracer/images/boomer.jpg")
+            ),
+            "../images/boomer.jpg"
+        );
+        assert_eq!(
+            relative_href(Path::new("out/./svg"), Path::new("my images/#1 é.jpg")),
+            "../../my%20images/%231%20%C3%A9.jpg"
+        );
+        assert_eq!(
+            relative_href(Path::new("/data/svg"), Path::new("/data/svg/../a.png")),
+            "../a.png"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::{AnnotatedImage, Annotation, Project},
+    vec::Vec2,
+};
+
+use super::{file_name, label_path, FormatError};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "annotation")]
+pub struct VocAnnotation {
+    #[serde(default)]
+    pub folder: String,
+    pub filename: String,
+    #[serde(default)]
+    pub path: String,
+    pub size: VocSize,
+    #[serde(default)]
+    pub segmented: u8,
+    #[serde(rename = "object", default)]
+    pub objects: Vec<VocObject>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VocSize {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_depth")]
+    pub depth: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VocObject {
+    pub name: String,
+    #[serde(default)]
+    pub pose: String,
+    #[serde(default)]
+    pub truncated: u8,
+    #[serde(default)]
+    pub difficult: u8,
+    pub bndbox: VocBox,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polygon: Option<VocPolygon>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VocBox {
+    pub xmin: f32,
+    pub ymin: f32,
+    pub xmax: f32,
+    pub ymax: f32,
+}
+
+/// Non-standard extension carrying the full outline next to the bounding box,
+/// VOC readers that do not know about it simply ignore the element.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VocPolygon {
+    #[serde(rename = "pt", default)]
+    pub points: Vec<VocPoint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VocPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+fn default_depth() -> u32 {
+    3
+}
+
+pub fn export(project: &Project, image: &AnnotatedImage) -> VocAnnotation {
+    let path = Path::new(&image.path);
+    VocAnnotation {
+        folder: path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .and_then(|folder| folder.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        filename: file_name(&image.path),
+        path: image.path.clone(),
+        size: VocSize {
+            width: image.width,
+            height: image.height,
+            depth: default_depth(),
+        },
+        segmented: 0,
+        objects: image
+            .annotations
+            .iter()
+            .filter_map(|annotation| export_annotation(project, annotation))
+            .collect(),
+    }
+}
+
+/// Writes one XML file per image of the project into `directory`, named after
+/// the image file. Returns the number of annotations written without their
+/// holes, which VOC objects cannot hold.
+pub fn save(project: &Project, directory: &str) -> Result<usize, FormatError> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+
+    for image in &project.images {
+        let mut xml = String::new();
+        let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+        serializer.indent(' ', 2);
+        export(project, image).serialize(serializer)?;
+
+        fs::write(label_path(directory, &image.path, "xml"), xml)?;
+    }
+    Ok(project
+        .images
+        .iter()
+        .flat_map(|image| &image.annotations)
+        .filter(|annotation| annotation.polygon.len() >= 3 && !annotation.holes.is_empty())
+        .count())
+}
+
+pub fn load(filename: &str) -> Result<VocAnnotation, FormatError> {
+    let xml = fs::read_to_string(filename)?;
+    Ok(quick_xml::de::from_str(&xml)?)
+}
+
+/// Adds the objects of the VOC file to the given project image. Objects
+/// without the polygon extension become rectangles spanning their bounding
+/// box. Returns the number of annotations created.
+pub fn import(project: &mut Project, image: usize, voc: &VocAnnotation) -> usize {
+    let mut imported = 0;
+    for object in &voc.objects {
+        let polygon = import_polygon(object);
+        if polygon.len() < 3 {
+            continue;
+        }
+        let label = project.add_label(&object.name);
+        if project.create_annotation(image, label, polygon).is_some() {
+            imported += 1;
+        }
+    }
+    imported
+}
+
+/// Imports the XML files in `directory` belonging to the images of the
+/// project, images without one are left untouched. Files are matched to
+/// images by name, whatever `<filename>` they hold.
+pub fn import_directory(project: &mut Project, directory: &str) -> Result<usize, FormatError> {
+    let mut imported = 0;
+    for image in 0..project.images.len() {
+        let filename = label_path(Path::new(directory), &project.images[image].path, "xml");
+        if filename.exists() {
+            let voc = load(&filename.to_string_lossy())?;
+            imported += import(project, image, &voc);
+        }
+    }
+    Ok(imported)
+}
+
+fn export_annotation(project: &Project, annotation: &Annotation) -> Option<VocObject> {
+    if annotation.polygon.len() < 3 {
+        return None;
+    }
+    let (min, max) = annotation.bounding_box()?;
+
+    Some(VocObject {
+        name: project.labels.get(annotation.label)?.name.clone(),
+        pose: String::from("Unspecified"),
+        truncated: 0,
+        difficult: 0,
+        bndbox: VocBox {
+            xmin: min.x.floor(),
+            ymin: min.y.floor(),
+            xmax: max.x.ceil(),
+            ymax: max.y.ceil(),
+        },
+        polygon: Some(VocPolygon {
+            points: annotation
+                .polygon
+                .iter()
+                .map(|point| VocPoint {
+                    x: point.x,
+                    y: point.y,
+                })
+                .collect(),
+        }),
+    })
+}
+
+fn import_polygon(object: &VocObject) -> Vec<Vec2> {
+    match &object.polygon {
+        Some(polygon) => polygon
+            .points
+            .iter()
+            .map(|point| Vec2::new((point.x, point.y)))
+            .collect(),
+        None => {
+            let VocBox {
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+            } = object.bndbox;
+            vec![
+                Vec2::new((xmin, ymin)),
+                Vec2::new((xmax, ymin)),
+                Vec2::new((xmax, ymax)),
+                Vec2::new((xmin, ymax)),
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::project_with_triangle;
+
+    #[test]
+    fn exports_bounding_box_and_polygon() {
+        let (project, _) = project_with_triangle();
+        let voc = export(&project, &project.images[0]);
+
+        assert_eq!(voc.folder, "images");
+        assert_eq!(voc.filename, "boomer.jpg");
+        assert_eq!((voc.size.width, voc.size.height), (640, 480));
+
+        let object = &voc.objects[0];
+        assert_eq!(object.name, "gmd");
+        assert_eq!(
+            object.bndbox,
+            VocBox {
+                xmin: 10.0,
+                ymin: 20.0,
+                xmax: 50.0,
+                ymax: 61.0,
+            }
+        );
+        assert_eq!(object.polygon.as_ref().unwrap().points.len(), 3);
+    }
+
+    #[test]
+    fn xml_round_trip_restores_polygons() {
+        let (project, _) = project_with_triangle();
+        let voc = export(&project, &project.images[0]);
+        let xml = quick_xml::se::to_string(&voc).unwrap();
+        let parsed: VocAnnotation = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(parsed, voc);
+
+        let mut imported = Project::new(&["gmd"]);
+        imported.add_image("boomer.jpg", 640, 480);
+        assert_eq!(import(&mut imported, 0, &parsed), 1);
+        assert_eq!(
+            imported.images[0].annotations[0].polygon,
+            project.images[0].annotations[0].polygon
+        );
+    }
+
+    #[test]
+    fn plain_voc_objects_become_rectangles() {
+        let xml = r#"
+            <annotation>
+                <filename>boomer.jpg</filename>
+                <size><width>640</width><height>480</height><depth>3</depth></size>
+                <object>
+                    <name>dog</name>
+                    <bndbox><xmin>48</xmin><ymin>240</ymin><xmax>195</xmax><ymax>371</ymax></bndbox>
+                </object>
+            </annotation>
+        "#;
+        let voc: VocAnnotation = quick_xml::de::from_str(xml).unwrap();
+
+        // The file names the image it was made for, which may have been renamed.
+        let mut project = Project::new(&["jp2"]);
+        let image = project.add_image("renamed.jpg", 640, 480);
+        assert_eq!(import(&mut project, image, &voc), 1);
+
+        let annotation = &project.images[0].annotations[0];
+        assert_eq!(annotation.label, 1);
+        assert_eq!(annotation.area(), 147.0 * 131.0);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{fmt::Write, fs, path::Path};
+
+use crate::annotation::{AnnotatedImage, Annotation, Project};
+
+use super::{label_path, FormatError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YoloFlavour {
+    Segmentation,
+    Detection,
+}
+
+/// Builds the label file of a single image, one line per annotation holding the
+/// class index followed by coordinates normalized to the image size.
+pub fn export_image(image: &AnnotatedImage, flavour: YoloFlavour) -> String {
+    image
+        .annotations
+        .iter()
+        .filter_map(|annotation| export_annotation(image, annotation, flavour))
+        .fold(String::new(), |mut labels, line| {
+            labels.push_str(&line);
+            labels.push('\n');
+            labels
+        })
+}
+
+pub fn data_yaml(project: &Project) -> String {
+    let mut yaml = format!("nc: {}\nnames:\n", project.labels.len());
+    project
+        .labels
+        .iter()
+        .enumerate()
+        .for_each(|(index, label)| {
+            let _ = writeln!(yaml, "  {}: '{}'", index, label.name.replace('\'', "''"));
+        });
+    yaml
+}
+
+/// Writes a `.txt` label file per image together with `data.yaml` listing the
+/// classes into `directory`. Returns the number of annotations written without
+/// their holes, which segmentation polygons cannot hold and detection boxes
+/// do not need.
+pub fn save(
+    project: &Project,
+    directory: &str,
+    flavour: YoloFlavour,
+) -> Result<usize, FormatError> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+
+    for image in &project.images {
+        fs::write(
+            label_path(directory, &image.path, "txt"),
+            export_image(image, flavour),
+        )?;
+    }
+    fs::write(directory.join("data.yaml"), data_yaml(project))?;
+    if flavour == YoloFlavour::Detection {
+        return Ok(0);
+    }
+    Ok(project
+        .images
+        .iter()
+        .flat_map(|image| &image.annotations)
+        .filter(|annotation| annotation.polygon.len() >= 3 && !annotation.holes.is_empty())
+        .count())
+}
+
+fn export_annotation(
+    image: &AnnotatedImage,
+    annotation: &Annotation,
+    flavour: YoloFlavour,
+) -> Option<String> {
+    if annotation.polygon.len() < 3 || image.width == 0 || image.height == 0 {
+        return None;
+    }
+    let (width, height) = (image.width as f32, image.height as f32);
+    let normalize = |x: f32, y: f32| ((x / width).clamp(0.0, 1.0), (y / height).clamp(0.0, 1.0));
+
+    let coordinates = match flavour {
+        YoloFlavour::Segmentation => annotation
+            .polygon
+            .iter()
+            .flat_map(|point| {
+                let (x, y) = normalize(point.x, point.y);
+                [x, y]
+            })
+            .collect(),
+        YoloFlavour::Detection => {
+            let (min, max) = annotation.bounding_box()?;
+            let (min_x, min_y) = normalize(min.x, min.y);
+            let (max_x, max_y) = normalize(max.x, max.y);
+            vec![
+                (min_x + max_x) * 0.5,
+                (min_y + max_y) * 0.5,
+                max_x - min_x,
+                max_y - min_y,
+            ]
+        }
+    };
+
+    Some(
+        coordinates
+            .iter()
+            .fold(annotation.label.to_string(), |mut line, coordinate| {
+                let _ = write!(line, " {:.6}", coordinate);
+                line
+            }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::project_with_square;
+
+    #[test]
+    fn segmentation_labels_are_normalized_polygons() {
+        let project = project_with_square();
+        assert_eq!(
+            export_image(&project.images[0], YoloFlavour::Segmentation),
+            "1 0.015625 0.041667 0.078125 0.041667 0.078125 0.125000 0.015625 0.125000\n"
+        );
+    }
+
+    #[test]
+    fn detection_labels_are_normalized_boxes() {
+        let project = project_with_square();
+        assert_eq!(
+            export_image(&project.images[0], YoloFlavour::Detection),
+            "1 0.046875 0.083333 0.062500 0.083333\n"
+        );
+    }
+
+    #[test]
+    fn data_yaml_lists_classes_in_order() {
+        let project = Project::new(&["jp2", "it's"]);
+        assert_eq!(
+            data_yaml(&project),
+            "nc: 2\nnames:\n  0: 'jp2'\n  1: 'it''s'\n"
+        );
+    }
+}
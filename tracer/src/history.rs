@@ -0,0 +1,626 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    annotation::{Annotation, AnnotationId, LabelClass, Project},
+    vec::Vec2,
+};
+
+/// Number of commands kept by `History::default`.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// Addresses a single point of an annotation, either on its outline or on
+/// one of its holes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexRef {
+    pub image: usize,
+    pub annotation: AnnotationId,
+    pub hole: Option<usize>,
+    pub index: usize,
+}
+
+/// The outline and holes of an annotation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape {
+    pub polygon: Vec<Vec2>,
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+/// A reversible edit of the project.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    AddPoint {
+        vertex: VertexRef,
+        point: Vec2,
+    },
+    MovePoint {
+        vertex: VertexRef,
+        from: Vec2,
+        to: Vec2,
+    },
+    DeletePoint {
+        vertex: VertexRef,
+        point: Vec2,
+    },
+    CreateAnnotation {
+        image: usize,
+        annotation: Annotation,
+    },
+    DeleteAnnotation {
+        image: usize,
+        index: usize,
+        annotation: Annotation,
+    },
+    ChangeLabel {
+        image: usize,
+        annotation: AnnotationId,
+        from: usize,
+        to: usize,
+    },
+    /// Replaces every point of the annotation at once, as moving, rotating or
+    /// scaling it does.
+    Reshape {
+        image: usize,
+        annotation: AnnotationId,
+        from: Shape,
+        to: Shape,
+    },
+    /// Annotations and labels an import added to the project, the labels
+    /// from index `first_label` on.
+    Import {
+        annotations: Vec<(usize, Annotation)>,
+        first_label: usize,
+        labels: Vec<LabelClass>,
+    },
+}
+
+/// Undo and redo stacks of commands, the oldest commands are forgotten once
+/// more than `capacity` have been executed.
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    capacity: usize,
+}
+
+impl Shape {
+    pub fn of(annotation: &Annotation) -> Self {
+        Shape {
+            polygon: annotation.polygon.clone(),
+            holes: annotation.holes.clone(),
+        }
+    }
+}
+
+impl Command {
+    /// Describes deleting the annotation as it currently is in the project.
+    pub fn delete_annotation(project: &Project, image: usize, id: AnnotationId) -> Option<Self> {
+        let annotations = &project.image(image)?.annotations;
+        let index = annotations
+            .iter()
+            .position(|annotation| annotation.id == id)?;
+        Some(Command::DeleteAnnotation {
+            image,
+            index,
+            annotation: annotations[index].clone(),
+        })
+    }
+
+    /// Describes deleting the point as it currently is in the project, unless
+    /// that would leave its ring with less than three points.
+    pub fn delete_point(project: &Project, vertex: VertexRef) -> Option<Self> {
+        let annotation = project.image(vertex.image)?.annotation(vertex.annotation)?;
+        let ring = match vertex.hole {
+            Some(hole) => annotation.holes.get(hole)?,
+            None => &annotation.polygon,
+        };
+        if ring.len() <= 3 {
+            return None;
+        }
+        Some(Command::DeletePoint {
+            vertex,
+            point: *ring.get(vertex.index)?,
+        })
+    }
+
+    pub fn apply(&self, project: &mut Project) -> Option<()> {
+        match self {
+            Command::AddPoint { vertex, point } => {
+                let ring = ring_mut(project, vertex)?;
+                (vertex.index <= ring.len()).then(|| ring.insert(vertex.index, *point))
+            }
+            Command::MovePoint { vertex, to, .. } => {
+                *ring_mut(project, vertex)?.get_mut(vertex.index)? = *to;
+                Some(())
+            }
+            Command::DeletePoint { vertex, .. } => {
+                let ring = ring_mut(project, vertex)?;
+                (vertex.index < ring.len()).then(|| {
+                    ring.remove(vertex.index);
+                })
+            }
+            Command::CreateAnnotation { image, annotation } => project
+                .insert_annotation(*image, annotation.clone())
+                .map(|_| ()),
+            Command::DeleteAnnotation {
+                image, annotation, ..
+            } => project.remove_annotation(*image, annotation.id).map(|_| ()),
+            Command::ChangeLabel {
+                image,
+                annotation,
+                to,
+                ..
+            } => {
+                project
+                    .image_mut(*image)?
+                    .annotation_mut(*annotation)?
+                    .label = *to;
+                Some(())
+            }
+            Command::Reshape {
+                image,
+                annotation,
+                to,
+                ..
+            } => {
+                let annotation = project.image_mut(*image)?.annotation_mut(*annotation)?;
+                annotation.polygon = to.polygon.clone();
+                annotation.holes = to.holes.clone();
+                Some(())
+            }
+            Command::Import {
+                annotations,
+                first_label,
+                labels,
+            } => {
+                if *first_label > project.labels.len()
+                    || annotations
+                        .iter()
+                        .any(|(image, _)| project.image(*image).is_none())
+                {
+                    return None;
+                }
+                project
+                    .labels
+                    .splice(*first_label..*first_label, labels.iter().cloned());
+                annotations.iter().for_each(|(image, annotation)| {
+                    project.insert_annotation(*image, annotation.clone());
+                });
+                Some(())
+            }
+        }
+    }
+
+    pub fn revert(&self, project: &mut Project) -> Option<()> {
+        match self {
+            Command::AddPoint { vertex, point } => Command::DeletePoint {
+                vertex: *vertex,
+                point: *point,
+            }
+            .apply(project),
+            Command::MovePoint { vertex, from, to } => Command::MovePoint {
+                vertex: *vertex,
+                from: *to,
+                to: *from,
+            }
+            .apply(project),
+            Command::DeletePoint { vertex, point } => Command::AddPoint {
+                vertex: *vertex,
+                point: *point,
+            }
+            .apply(project),
+            Command::CreateAnnotation { image, annotation } => {
+                project.remove_annotation(*image, annotation.id).map(|_| ())
+            }
+            Command::DeleteAnnotation {
+                image,
+                index,
+                annotation,
+            } => {
+                let annotations = &mut project.image_mut(*image)?.annotations;
+                annotations.insert((*index).min(annotations.len()), annotation.clone());
+                Some(())
+            }
+            Command::ChangeLabel {
+                image,
+                annotation,
+                from,
+                to,
+            } => Command::ChangeLabel {
+                image: *image,
+                annotation: *annotation,
+                from: *to,
+                to: *from,
+            }
+            .apply(project),
+            Command::Reshape {
+                image,
+                annotation,
+                from,
+                to,
+            } => Command::Reshape {
+                image: *image,
+                annotation: *annotation,
+                from: to.clone(),
+                to: from.clone(),
+            }
+            .apply(project),
+            Command::Import {
+                annotations,
+                first_label,
+                labels,
+            } => {
+                let added = *first_label..*first_label + labels.len();
+                if project.labels.get(added.clone()) != Some(labels.as_slice())
+                    || annotations.iter().any(|(image, annotation)| {
+                        project
+                            .image(*image)
+                            .and_then(|image| image.annotation(annotation.id))
+                            .is_none()
+                    })
+                {
+                    return None;
+                }
+                annotations.iter().for_each(|(image, annotation)| {
+                    project.remove_annotation(*image, annotation.id);
+                });
+                project.labels.drain(added);
+                Some(())
+            }
+        }
+    }
+}
+
+fn ring_mut<'a>(project: &'a mut Project, vertex: &VertexRef) -> Option<&'a mut Vec<Vec2>> {
+    let annotation = project
+        .image_mut(vertex.image)?
+        .annotation_mut(vertex.annotation)?;
+    match vertex.hole {
+        Some(hole) => annotation.holes.get_mut(hole),
+        None => Some(&mut annotation.polygon),
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: vec![],
+            capacity,
+        }
+    }
+
+    /// Applies the command and records it, dropping whatever could have been
+    /// redone. Commands that do not apply are not recorded.
+    pub fn execute(&mut self, project: &mut Project, command: Command) -> Option<()> {
+        command.apply(project)?;
+        self.push(command);
+        Some(())
+    }
+
+    /// Runs an import and records the annotations and labels it added to the
+    /// project as a single command.
+    pub fn import<T>(
+        &mut self,
+        project: &mut Project,
+        import: impl FnOnce(&mut Project) -> T,
+    ) -> T {
+        let labels = project.labels.len();
+        let existing: HashSet<AnnotationId> = project
+            .images
+            .iter()
+            .flat_map(|image| image.annotations.iter().map(|annotation| annotation.id))
+            .collect();
+
+        let result = import(project);
+
+        let annotations: Vec<(usize, Annotation)> = project
+            .images
+            .iter()
+            .enumerate()
+            .flat_map(|(index, image)| {
+                image
+                    .annotations
+                    .iter()
+                    .filter(|annotation| !existing.contains(&annotation.id))
+                    .map(move |annotation| (index, annotation.clone()))
+            })
+            .collect();
+        let first_label = labels;
+        let labels = project
+            .labels
+            .get(first_label..)
+            .unwrap_or_default()
+            .to_vec();
+        if !annotations.is_empty() || !labels.is_empty() {
+            self.push(Command::Import {
+                annotations,
+                first_label,
+                labels,
+            });
+        }
+        result
+    }
+
+    /// Reverts the last command, returning it. Neither the project nor the
+    /// stacks change when the command no longer reverts, as when the project
+    /// was edited without going through the history.
+    pub fn undo(&mut self, project: &mut Project) -> Option<&Command> {
+        self.undo.back()?.revert(project)?;
+        let command = self.undo.pop_back()?;
+        self.redo.push(command);
+        self.redo.last()
+    }
+
+    /// Applies the last undone command again, returning it. Like `undo`,
+    /// nothing changes when the command no longer applies.
+    pub fn redo(&mut self, project: &mut Project) -> Option<&Command> {
+        self.redo.last()?.apply(project)?;
+        let command = self.redo.pop()?;
+        self.undo.push_back(command);
+        self.undo.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, command: Command) {
+        self.redo.clear();
+        self.undo.push_back(command);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::project_with_triangle;
+
+    fn point(x: f32, y: f32) -> Vec2 {
+        Vec2::new((x, y))
+    }
+
+    fn vertex(annotation: AnnotationId, index: usize) -> VertexRef {
+        VertexRef {
+            image: 0,
+            annotation,
+            hole: None,
+            index,
+        }
+    }
+
+    #[test]
+    fn point_edits_undo_and_redo() {
+        let (mut project, id) = project_with_triangle();
+        let original = project.clone();
+        let mut history = History::default();
+
+        history
+            .execute(
+                &mut project,
+                Command::AddPoint {
+                    vertex: vertex(id, 1),
+                    point: point(30.0, 0.0),
+                },
+            )
+            .unwrap();
+        history
+            .execute(
+                &mut project,
+                Command::MovePoint {
+                    vertex: vertex(id, 0),
+                    from: point(10.5, 20.0),
+                    to: point(9.0, 19.0),
+                },
+            )
+            .unwrap();
+        let delete = Command::delete_point(&project, vertex(id, 3)).unwrap();
+        history.execute(&mut project, delete).unwrap();
+        assert_eq!(Command::delete_point(&project, vertex(id, 0)), None);
+        let edited = project.clone();
+        assert_eq!(
+            edited.images[0].annotations[0].polygon,
+            vec![point(9.0, 19.0), point(30.0, 0.0), point(50.0, 20.25)]
+        );
+
+        while history.undo(&mut project).is_some() {}
+        assert_eq!(project, original);
+        while history.redo(&mut project).is_some() {}
+        assert_eq!(project, edited);
+    }
+
+    #[test]
+    fn annotation_edits_undo_and_redo() {
+        let (mut project, id) = project_with_triangle();
+        let mut history = History::default();
+        let created = project.new_annotation(1, vec![point(1.0, 1.0)]);
+        history
+            .execute(
+                &mut project,
+                Command::CreateAnnotation {
+                    image: 0,
+                    annotation: created.clone(),
+                },
+            )
+            .unwrap();
+        history
+            .execute(
+                &mut project,
+                Command::ChangeLabel {
+                    image: 0,
+                    annotation: id,
+                    from: 1,
+                    to: 0,
+                },
+            )
+            .unwrap();
+        let delete = Command::delete_annotation(&project, 0, id).unwrap();
+        history.execute(&mut project, delete).unwrap();
+        assert_eq!(project.images[0].annotations, vec![created.clone()]);
+
+        history.undo(&mut project);
+        let annotations = &project.images[0].annotations;
+        assert_eq!((annotations[0].id, annotations[0].label), (id, 0));
+        assert_eq!(annotations[1], created);
+
+        history.undo(&mut project);
+        history.undo(&mut project);
+        // Ids stay taken so annotations created later never reuse them.
+        assert_eq!(project.images, project_with_triangle().0.images);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn reshape_replaces_outline_and_holes() {
+        let (mut project, id) = project_with_triangle();
+        let original = project.clone();
+        let mut history = History::default();
+        let moved = Shape {
+            polygon: vec![point(5.0, 5.0), point(15.0, 5.0), point(5.0, 15.0)],
+            holes: vec![vec![point(6.0, 6.0), point(8.0, 6.0), point(6.0, 8.0)]],
+        };
+        let from = Shape::of(&project.images[0].annotations[0]);
+
+        history
+            .execute(
+                &mut project,
+                Command::Reshape {
+                    image: 0,
+                    annotation: id,
+                    from,
+                    to: moved.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(Shape::of(&project.images[0].annotations[0]), moved);
+
+        history.undo(&mut project);
+        assert_eq!(project, original);
+    }
+
+    #[test]
+    fn import_is_undone_as_a_whole() {
+        let (mut project, _) = project_with_triangle();
+        let original = project.clone();
+        let mut history = History::default();
+
+        let imported = history.import(&mut project, |project| {
+            let label = project.add_label("wmts");
+            project.create_annotation(0, label, vec![point(2.0, 2.0)]);
+            project.create_annotation(0, 0, vec![point(3.0, 3.0)]);
+            2
+        });
+        assert_eq!(imported, 2);
+        let after = project.clone();
+
+        history.undo(&mut project).unwrap();
+        assert_eq!(project.images, original.images);
+        assert_eq!(project.labels, original.labels);
+        history.redo(&mut project).unwrap();
+        assert_eq!(project, after);
+
+        history.import(&mut project, |_| ());
+        assert!(history.can_undo() && !history.can_redo());
+    }
+
+    #[test]
+    fn history_is_bounded_and_new_commands_drop_redo() {
+        let (mut project, id) = project_with_triangle();
+        let mut history = History::new(2);
+        (1..4).for_each(|label| {
+            history.execute(
+                &mut project,
+                Command::ChangeLabel {
+                    image: 0,
+                    annotation: id,
+                    from: label,
+                    to: label + 1,
+                },
+            );
+        });
+
+        assert!(history.undo(&mut project).is_some());
+        assert!(history.undo(&mut project).is_some());
+        assert!(history.undo(&mut project).is_none());
+        assert_eq!(project.images[0].annotations[0].label, 2);
+
+        history.execute(
+            &mut project,
+            Command::ChangeLabel {
+                image: 0,
+                annotation: id,
+                from: 2,
+                to: 0,
+            },
+        );
+        assert!(!history.can_redo());
+        assert!(history
+            .execute(
+                &mut project,
+                Command::MovePoint {
+                    vertex: vertex(id, 7),
+                    from: point(0.0, 0.0),
+                    to: point(1.0, 1.0),
+                },
+            )
+            .is_none());
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn failed_steps_leave_the_stacks_alone() {
+        let (mut project, id) = project_with_triangle();
+        let mut history = History::default();
+        history
+            .execute(
+                &mut project,
+                Command::AddPoint {
+                    vertex: vertex(id, 3),
+                    point: point(1.0, 1.0),
+                },
+            )
+            .unwrap();
+
+        project.remove_annotation(0, id).unwrap();
+        let edited = project.clone();
+        assert!(history.undo(&mut project).is_none());
+        assert_eq!(project, edited);
+        assert!(history.can_undo() && !history.can_redo());
+    }
+
+    #[test]
+    fn import_undo_removes_its_own_labels() {
+        let (mut project, _) = project_with_triangle();
+        let mut history = History::default();
+        history.import(&mut project, |project| {
+            let label = project.add_label("wmts");
+            project.create_annotation(0, label, vec![point(2.0, 2.0)]);
+        });
+        project.add_label("later");
+
+        history.undo(&mut project).unwrap();
+        let names: Vec<_> = project.labels.iter().map(|label| &label.name).collect();
+        assert_eq!(names, ["jp2", "gmd", "later"]);
+
+        history.redo(&mut project).unwrap();
+        assert_eq!(project.find_label("wmts"), Some(2));
+        assert_eq!(project.images[0].annotations[1].label, 2);
+    }
+}
@@ -0,0 +1,146 @@
+/// Keys the UI reacts to. Printable keys are identified by the character on
+/// an unshifted US layout, letters in lowercase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    F(u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub super_key: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Input delivered to the application together with the cursor position in
+/// normalized screen space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyPress {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    KeyRepeat {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    KeyRelease {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    /// Character typed by the user, after keyboard layout and modifiers.
+    Text(char),
+    MousePress {
+        button: MouseButton,
+        modifiers: Modifiers,
+    },
+    MouseRelease {
+        button: MouseButton,
+        modifiers: Modifiers,
+    },
+    MouseMove,
+    /// Cursor movement while a button is held, `delta` in screen space.
+    Drag {
+        button: MouseButton,
+        delta: (f32, f32),
+    },
+    Scroll(f32),
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        control: false,
+        alt: false,
+        super_key: false,
+    };
+
+    /// Maps GLFW modifier bits onto modifiers, the lock keys are ignored.
+    pub fn from_glfw_bits(bits: i32) -> Modifiers {
+        Modifiers {
+            shift: bits & 0x1 != 0,
+            control: bits & 0x2 != 0,
+            alt: bits & 0x4 != 0,
+            super_key: bits & 0x8 != 0,
+        }
+    }
+}
+
+impl InputEvent {
+    /// Whether the event targets the element under the cursor. The other
+    /// events are offered to the whole element tree until one handles them,
+    /// so releases and drags reach an element even after the cursor left it.
+    pub fn is_positional(&self) -> bool {
+        matches!(
+            self,
+            InputEvent::MousePress { .. } | InputEvent::MouseMove | InputEvent::Scroll(_)
+        )
+    }
+}
+
+impl Key {
+    /// Maps a GLFW key code onto a key, `None` for keys the UI ignores.
+    pub fn from_glfw_code(code: i32) -> Option<Key> {
+        match code {
+            32..=96 => {
+                let c = char::from_u32(code as u32)?;
+                Some(Key::Char(c.to_ascii_lowercase()))
+            }
+            256 => Some(Key::Escape),
+            257 => Some(Key::Enter),
+            258 => Some(Key::Tab),
+            259 => Some(Key::Backspace),
+            260 => Some(Key::Insert),
+            261 => Some(Key::Delete),
+            262 => Some(Key::Right),
+            263 => Some(Key::Left),
+            264 => Some(Key::Down),
+            265 => Some(Key::Up),
+            266 => Some(Key::PageUp),
+            267 => Some(Key::PageDown),
+            268 => Some(Key::Home),
+            269 => Some(Key::End),
+            290..=314 => Some(Key::F((code - 289) as u8)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glfw_codes_map_to_keys() {
+        assert_eq!(Key::from_glfw_code(65), Some(Key::Char('a')));
+        assert_eq!(Key::from_glfw_code(32), Some(Key::Char(' ')));
+        assert_eq!(Key::from_glfw_code(49), Some(Key::Char('1')));
+        assert_eq!(Key::from_glfw_code(256), Some(Key::Escape));
+        assert_eq!(Key::from_glfw_code(290), Some(Key::F(1)));
+        assert_eq!(Key::from_glfw_code(340), None);
+        assert_eq!(Key::from_glfw_code(-1), None);
+    }
+}
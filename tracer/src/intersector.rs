@@ -1,4 +1,4 @@
-use crate::vec::Vec2;
+use crate::vec::{predicates::orient2d, Vec2};
 
 pub struct Intersector {
     vertices: Vec<Vec2>,
@@ -7,13 +7,17 @@ pub struct Intersector {
 
 #[derive(Debug)]
 pub struct Segment<T> {
-    start: T,
-    end: T,
+    pub start: T,
+    pub end: T,
 }
 
 impl Intersector {
-    pub fn from_vertices(vertices: &Vec<Vec2>) -> Option<Self> {
-        let vertices = vertices.clone();
+    pub fn from_vertices(vertices: &[Vec2]) -> Option<Self> {
+        if vertices.len() < 3 {
+            return None;
+        }
+
+        let vertices = vertices.to_vec();
         let segments = (0..vertices.len())
             .map(|i| Segment {
                 start: i,
@@ -25,66 +29,60 @@ impl Intersector {
     }
 
     pub fn get_closed_loops(&mut self) -> Vec<Vec<Vec2>> {
-        let cycles = vec![];
+        let mut cycles = vec![];
 
         self.resolve_all_intersections();
 
-        while let Some(mut root) = self.find_root_segment() {
+        let mut visited = vec![false; self.segments.len()];
+        while let Some(mut root) = Self::find_root_segment(&visited) {
             let mut cycle = vec![];
 
-            cycle.push(self.vertices[self.segments[root].start]);
-            self.segments[root].start = 21370;
-            self.segments[root].end = 21370;
-            while let Some(segment) = self.find_next_segment(&self.segments[root]) {
-                cycle.push(self.vertices[self.segments[segment].start]);
-                self.segments[segment].start = 21370;
-                self.segments[segment].end = 21370;
-                root = segment;
+            while !visited[root] {
+                visited[root] = true;
+                cycle.push(self.vertices[self.segments[root].start]);
+                match self.find_next_segment(&self.segments[root]) {
+                    Some(segment) => root = segment,
+                    None => break,
+                }
+            }
+
+            if let Some(cycle) = Self::normalize_loop(cycle) {
+                cycles.push(cycle);
             }
         }
         cycles
     }
 
-    fn find_root_segment(&self) -> Option<usize> {
-        Some(
-            self.segments
-                .iter()
-                .enumerate()
-                .find(|(i, s)| s.start != 21370)?
-                .0,
-        )
+    fn find_root_segment(visited: &[bool]) -> Option<usize> {
+        visited.iter().position(|visited| !visited)
     }
 
     fn find_next_segment(&self, seg: &Segment<usize>) -> Option<usize> {
-        Some(
-            self.segments
-                .iter()
-                .enumerate()
-                .find(|(i, s)| s.start == seg.end)?
-                .0,
-        )
+        self.segments.iter().position(|s| s.start == seg.end)
     }
 
     fn resolve_all_intersections(&mut self) {
-        while let Some(intersection) = self.find_intersection() {
-            let s1 = &self.segments[intersection.0];
-            let s2 = &self.segments[intersection.1];
+        // Every split removes one crossing, so a valid outline never needs
+        // more than n^2 of them, the bound only guards against float noise
+        let max_splits = self.segments.len() * self.segments.len();
+
+        for _ in 0..max_splits {
+            match self.find_intersection() {
+                Some((a, b)) => self.insert_missing_intersection(a, b),
+                None => break,
+            }
         }
     }
 
-    fn insert_missing_intersection(&mut self, a: &Segment<usize>, b: &Segment<usize>) {
-        let segment_a = self.to_segment(a);
-        let segment_b = self.to_segment(b);
+    fn insert_missing_intersection(&mut self, a: usize, b: usize) {
+        let b = self.segments.remove(b);
+        let a = self.segments.remove(a);
 
-        self.vertices
-            .push(Self::create_intersection(&segment_a, &segment_b));
-        self.vertices
-            .push(Self::create_intersection(&segment_a, &segment_b));
+        let point = Self::create_intersection(&self.to_segment(&a), &self.to_segment(&b));
+        self.vertices.push(point);
+        self.vertices.push(point);
 
-        let intersection = (self.vertices.len() - 1, self.vertices.len() - 2);
-
-        self.segments.remove(intersection.0);
-        self.segments.remove(intersection.1);
+        let intersection = (self.vertices.len() - 2, self.vertices.len() - 1);
 
         self.segments.push(Segment {
             start: a.start,
@@ -107,7 +105,7 @@ impl Intersector {
 
     fn find_intersection(&self) -> Option<(usize, usize)> {
         for i in 0..self.segments.len() {
-            for j in i..self.segments.len() {
+            for j in (i + 1)..self.segments.len() {
                 let segment_a = self.to_segment(&self.segments[i]);
                 let segment_b = self.to_segment(&self.segments[j]);
 
@@ -126,22 +124,63 @@ impl Intersector {
         }
     }
 
-    fn segments_intersect(a: &Segment<Vec2>, b: &Segment<Vec2>) -> bool {
-        let (a_norm, b_norm) = ((a.end - a.start).perp(), (b.end - b.start).perp());
+    fn normalize_loop(cycle: Vec<Vec2>) -> Option<Vec<Vec2>> {
+        let mut cycle: Vec<_> = cycle
+            .iter()
+            .enumerate()
+            .filter(|(i, point)| {
+                let next = cycle[(i + 1) % cycle.len()];
+                point.x != next.x || point.y != next.y
+            })
+            .map(|(_, point)| *point)
+            .collect();
+
+        if cycle.len() < 3 {
+            return None;
+        }
+
+        let area = Self::signed_area(&cycle);
+        if area == 0.0 {
+            return None;
+        }
+        if area < 0.0 {
+            cycle.reverse();
+        }
+        Some(cycle)
+    }
+
+    pub fn signed_area(cycle: &[Vec2]) -> f32 {
+        let doubled_area = (0..cycle.len()).fold(0.0, |area, i| {
+            area + cycle[i].cross(cycle[(i + 1) % cycle.len()])
+        });
+        doubled_area * 0.5
+    }
+
+    pub fn segments_intersect(a: &Segment<Vec2>, b: &Segment<Vec2>) -> bool {
         let (a_span, b_span) = (
-            (a.end - b.start, a.start - b.start),
-            (b.end - a.start, b.start - a.start),
+            (
+                orient2d(a.start, a.end, b.start),
+                orient2d(a.start, a.end, b.end),
+            ),
+            (
+                orient2d(b.start, b.end, a.start),
+                orient2d(b.start, b.end, a.end),
+            ),
         );
 
-        (a_span.0 * b_norm) * (a_span.1 * b_norm) < -0.0
-            && (b_span.0 * a_norm) * (b_span.1 * a_norm) < 0.0
+        a_span.0 * a_span.1 < 0.0 && b_span.0 * b_span.1 < 0.0
     }
 
     fn create_intersection(a: &Segment<Vec2>, b: &Segment<Vec2>) -> Vec2 {
         let norm = (a.end - a.start).perp();
-        let f_start = (b.end * norm).abs();
-        let f_end = (b.start * norm).abs();
+        let f_start = ((b.end - a.start) * norm).abs();
+        let f_end = ((b.start - a.start) * norm).abs();
         let sum = f_start + f_end;
+        // Rounding can put both ends of b on the line through a, any point of
+        // b is then as close to the crossing as the floats can tell
+        if sum == 0.0 || !sum.is_finite() {
+            return b.start;
+        }
 
         Vec2::new((
             (f_start * b.start.x + f_end * b.end.x) / sum,
@@ -153,6 +192,7 @@ impl Intersector {
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn segments_should_intersect() {
@@ -252,4 +292,107 @@ mod test {
         assert_eq!(point.x, 0.5, "Invalid coordinate for: {:?}", segments[1]);
         assert_eq!(point.y, 0.5, "Invalid coordinate for: {:?}", segments[1]);
     }
+
+    #[test]
+    fn collinear_or_degenerate_segments_give_a_finite_intersection() {
+        let segments = [
+            (
+                Segment {
+                    start: Vec2::new((0.0, 0.0)),
+                    end: Vec2::new((2.0, 0.0)),
+                },
+                Segment {
+                    start: Vec2::new((1.0, 0.0)),
+                    end: Vec2::new((3.0, 0.0)),
+                },
+            ),
+            (
+                Segment {
+                    start: Vec2::new((1.0, 1.0)),
+                    end: Vec2::new((1.0, 1.0)),
+                },
+                Segment {
+                    start: Vec2::new((0.0, 1.0)),
+                    end: Vec2::new((2.0, 1.0)),
+                },
+            ),
+        ];
+        for segment_pair in segments {
+            let point = Intersector::create_intersection(&segment_pair.0, &segment_pair.1);
+            assert_eq!(point, segment_pair.1.start, "Fails: {:?}", segment_pair);
+        }
+    }
+
+    #[test]
+    fn simple_polygon_yields_single_loop() {
+        let square = vec![
+            Vec2::new((0.0, 0.0)),
+            Vec2::new((0.0, 1.0)),
+            Vec2::new((1.0, 1.0)),
+            Vec2::new((1.0, 0.0)),
+        ];
+        let loops = Intersector::from_vertices(&square).unwrap().get_closed_loops();
+
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 4);
+        assert_eq!(Intersector::signed_area(&loops[0]), 1.0);
+    }
+
+    #[test]
+    fn figure_eight_splits_into_two_loops() {
+        let figure_eight = vec![
+            Vec2::new((0.0, 0.0)),
+            Vec2::new((2.0, 2.0)),
+            Vec2::new((2.0, 0.0)),
+            Vec2::new((0.0, 2.0)),
+        ];
+        let loops = Intersector::from_vertices(&figure_eight)
+            .unwrap()
+            .get_closed_loops();
+
+        assert_eq!(loops.len(), 2);
+        for cycle in &loops {
+            assert_eq!(cycle.len(), 3, "Invalid loop: {:?}", cycle);
+            assert_eq!(Intersector::signed_area(cycle), 1.0, "Invalid loop: {:?}", cycle);
+        }
+    }
+
+    #[test]
+    fn duplicated_points_are_skipped() {
+        let triangle = vec![
+            Vec2::new((0.0, 0.0)),
+            Vec2::new((1.0, 0.0)),
+            Vec2::new((1.0, 0.0)),
+            Vec2::new((0.0, 1.0)),
+            Vec2::new((0.0, 1.0)),
+        ];
+        let loops = Intersector::from_vertices(&triangle).unwrap().get_closed_loops();
+
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn touching_or_collinear_segments_do_not_intersect(
+            a in (-16..16, -16..16),
+            b in (-16..16, -16..16),
+            c in (-16..16, -16..16),
+            t in (-4..4, -4..4),
+        ) {
+            let point = |(x, y): (i32, i32)| Vec2::new((x as f32, y as f32));
+            let (a, b, c) = (point(a), point(b), point(c));
+            let along = |t: i32| a + Vec2::new(((b.x - a.x) * t as f32, (b.y - a.y) * t as f32));
+
+            let touching = (Segment { start: a, end: b }, Segment { start: b, end: c });
+            let collinear = (
+                Segment { start: a, end: b },
+                Segment { start: along(t.0), end: along(t.1) },
+            );
+
+            prop_assert!(!Intersector::segments_intersect(&touching.0, &touching.1));
+            prop_assert!(!Intersector::segments_intersect(&touching.1, &touching.0));
+            prop_assert!(!Intersector::segments_intersect(&collinear.0, &collinear.1));
+        }
+    }
 }
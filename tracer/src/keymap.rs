@@ -0,0 +1,362 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::input::{Key, Modifiers};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ClosePolygon,
+    CancelPolygon,
+    DeleteSelection,
+    DeletePoint,
+    NewSelection,
+    Undo,
+    Redo,
+    CycleLabel,
+    NextImage,
+    PreviousImage,
+    ZoomToFit,
+    ActualSize,
+    ToggleEditMode,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug)]
+pub struct KeymapError {
+    pub error_msg: String,
+}
+
+/// Maps key presses onto editor actions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigBindings {
+    One(String),
+    Many(Vec<String>),
+}
+
+const ACTION_NAMES: [(&str, Action); 14] = [
+    ("close_polygon", Action::ClosePolygon),
+    ("cancel_polygon", Action::CancelPolygon),
+    ("delete_selection", Action::DeleteSelection),
+    ("delete_point", Action::DeletePoint),
+    ("new_selection", Action::NewSelection),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("cycle_label", Action::CycleLabel),
+    ("next_image", Action::NextImage),
+    ("previous_image", Action::PreviousImage),
+    ("zoom_to_fit", Action::ZoomToFit),
+    ("actual_size", Action::ActualSize),
+    ("toggle_edit_mode", Action::ToggleEditMode),
+    ("quit", Action::Quit),
+];
+
+const DEFAULT_BINDINGS: [(Action, &str); 15] = [
+    (Action::ClosePolygon, "Enter"),
+    (Action::CancelPolygon, "Escape"),
+    (Action::DeleteSelection, "Delete"),
+    (Action::DeletePoint, "Backspace"),
+    (Action::NewSelection, "n"),
+    (Action::Undo, "Ctrl+z"),
+    (Action::Redo, "Ctrl+Shift+z"),
+    (Action::Redo, "Ctrl+y"),
+    (Action::CycleLabel, "Tab"),
+    (Action::NextImage, "PageDown"),
+    (Action::PreviousImage, "PageUp"),
+    (Action::ZoomToFit, "f"),
+    (Action::ActualSize, "1"),
+    (Action::ToggleEditMode, "e"),
+    (Action::Quit, "Ctrl+q"),
+];
+
+/// Characters typed with Shift on a US layout and the key they are on.
+const SHIFTED_CHARS: [(char, char); 21] = [
+    ('~', '`'),
+    ('!', '1'),
+    ('@', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('^', '6'),
+    ('&', '7'),
+    ('*', '8'),
+    ('(', '9'),
+    (')', '0'),
+    ('_', '-'),
+    ('+', '='),
+    ('{', '['),
+    ('}', ']'),
+    ('|', '\\'),
+    (':', ';'),
+    ('"', '\''),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+];
+
+impl From<io::Error> for KeymapError {
+    fn from(from: io::Error) -> KeymapError {
+        KeymapError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+impl From<toml::de::Error> for KeymapError {
+    fn from(from: toml::de::Error) -> KeymapError {
+        KeymapError {
+            error_msg: from.to_string(),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, binding)| {
+                    (
+                        KeyBinding::parse(binding).expect("Invalid default key binding"),
+                        *action,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Parses a TOML keymap of `action = "binding"` or `action = ["binding",
+    /// ...]` entries. Actions listed replace their default bindings, the others
+    /// keep them.
+    pub fn parse(text: &str) -> Result<Self, KeymapError> {
+        let config: HashMap<String, ConfigBindings> = toml::from_str(text)?;
+        let mut keymap = Keymap::default();
+
+        for (name, bindings) in config {
+            let action = ACTION_NAMES
+                .iter()
+                .find(|(action_name, _)| *action_name == name)
+                .map(|(_, action)| *action)
+                .ok_or_else(|| KeymapError {
+                    error_msg: format!("Unknown action '{}'", name),
+                })?;
+            let bindings = match bindings {
+                ConfigBindings::One(binding) => vec![binding],
+                ConfigBindings::Many(bindings) => bindings,
+            };
+
+            keymap.bindings.retain(|_, bound| *bound != action);
+            for binding in bindings {
+                let binding = KeyBinding::parse(&binding).ok_or_else(|| KeymapError {
+                    error_msg: format!("Invalid key binding '{}' for '{}'", binding, name),
+                })?;
+                keymap.bindings.insert(binding, action);
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Reads the keymap from `filename`, falling back to the defaults when
+    /// there is no such file.
+    pub fn load(filename: &str) -> Result<Self, KeymapError> {
+        if !Path::new(filename).exists() {
+            return Ok(Keymap::default());
+        }
+        Self::parse(&fs::read_to_string(filename)?)
+    }
+
+    pub fn action(&self, key: Key, modifiers: Modifiers) -> Option<Action> {
+        self.bindings.get(&KeyBinding { key, modifiers }).copied()
+    }
+}
+
+impl KeyBinding {
+    /// Parses bindings such as `Ctrl+Shift+z`, `PageDown` or `F5`. Modifier and
+    /// key names are case insensitive.
+    pub fn parse(binding: &str) -> Option<Self> {
+        // The '+' key itself is written as a trailing "++" or a lone "+".
+        let (modifier_names, key) = match binding.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if binding.trim() == "+" => ("", "+"),
+            None => binding.rsplit_once('+').unwrap_or(("", binding)),
+        };
+
+        let mut modifiers = Modifiers::NONE;
+        for modifier in modifier_names
+            .split('+')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.control = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "super" | "cmd" | "meta" => modifiers.super_key = true,
+                _ => return None,
+            }
+        }
+
+        let key = Self::parse_key(key.trim())?;
+        // Keys are identified by their unshifted character, so a shifted
+        // character is the key below it with Shift held, as GLFW reports it.
+        let key = match key {
+            Key::Char(c) => match SHIFTED_CHARS.iter().find(|(shifted, _)| *shifted == c) {
+                Some((_, unshifted)) => {
+                    modifiers.shift = true;
+                    Key::Char(*unshifted)
+                }
+                None => key,
+            },
+            _ => key,
+        };
+        Some(KeyBinding { key, modifiers })
+    }
+
+    fn parse_key(name: &str) -> Option<Key> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(Key::Char(c.to_ascii_lowercase()));
+        }
+
+        let name = name.to_ascii_lowercase();
+        let key = match name.as_str() {
+            "space" => Key::Char(' '),
+            "escape" | "esc" => Key::Escape,
+            "enter" | "return" => Key::Enter,
+            "tab" => Key::Tab,
+            "backspace" => Key::Backspace,
+            "insert" => Key::Insert,
+            "delete" | "del" => Key::Delete,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            "home" => Key::Home,
+            "end" => Key::End,
+            _ => {
+                let number = name.strip_prefix('f')?.parse::<u8>().ok()?;
+                return (1..=25).contains(&number).then_some(Key::F(number));
+            }
+        };
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn control() -> Modifiers {
+        Modifiers {
+            control: true,
+            ..Modifiers::NONE
+        }
+    }
+
+    #[test]
+    fn parses_bindings() {
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Shift+Z"),
+            Some(KeyBinding {
+                key: Key::Char('z'),
+                modifiers: Modifiers {
+                    shift: true,
+                    ..control()
+                },
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++"),
+            Some(KeyBinding {
+                key: Key::Char('='),
+                modifiers: Modifiers {
+                    shift: true,
+                    ..control()
+                },
+            })
+        );
+        assert_eq!(
+            KeyBinding::parse("F12").map(|binding| binding.key),
+            Some(Key::F(12))
+        );
+        assert_eq!(
+            KeyBinding::parse("space").map(|binding| binding.key),
+            Some(Key::Char(' '))
+        );
+        assert_eq!(KeyBinding::parse("Hyper+a"), None);
+        assert_eq!(KeyBinding::parse("F26"), None);
+        assert_eq!(KeyBinding::parse("Nonsense"), None);
+    }
+
+    #[test]
+    fn shifted_characters_match_glfw_key_events() {
+        // GLFW reports Ctrl and '+' on a US layout as the '=' key with Ctrl
+        // and Shift held.
+        let key = Key::from_glfw_code(glfw::Key::Equal as i32).unwrap();
+        let modifiers =
+            Modifiers::from_glfw_bits((glfw::Modifiers::Control | glfw::Modifiers::Shift).bits());
+        let keymap = Keymap::parse(r#"zoom_to_fit = "ctrl++""#).unwrap();
+
+        assert_eq!(keymap.action(key, modifiers), Some(Action::ZoomToFit));
+    }
+
+    #[test]
+    fn defaults_cover_every_action() {
+        let keymap = Keymap::default();
+        ACTION_NAMES.iter().for_each(|(name, action)| {
+            assert!(
+                keymap.bindings.values().any(|bound| bound == action),
+                "no default binding for {}",
+                name
+            );
+        });
+        assert_eq!(keymap.action(Key::Char('z'), control()), Some(Action::Undo));
+        assert_eq!(keymap.action(Key::Char('z'), Modifiers::NONE), None);
+    }
+
+    #[test]
+    fn config_overrides_listed_actions() {
+        let keymap = Keymap::parse(
+            r#"
+                undo = "Ctrl+u"
+                next_image = ["Right", "Alt+n"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(keymap.action(Key::Char('u'), control()), Some(Action::Undo));
+        assert_eq!(keymap.action(Key::Char('z'), control()), None);
+        assert_eq!(
+            keymap.action(Key::Right, Modifiers::NONE),
+            Some(Action::NextImage)
+        );
+        assert_eq!(keymap.action(Key::PageDown, Modifiers::NONE), None);
+        assert_eq!(
+            keymap.action(Key::Enter, Modifiers::NONE),
+            Some(Action::ClosePolygon)
+        );
+    }
+
+    #[test]
+    fn config_errors_are_reported() {
+        assert!(Keymap::parse("fly = \"x\"").is_err());
+        assert!(Keymap::parse("undo = \"Ctrl+\"").is_err());
+        assert!(Keymap::parse("undo = ").is_err());
+    }
+}
@@ -0,0 +1,10 @@
+pub mod annotation;
+pub mod formats;
+pub mod history;
+pub mod input;
+pub mod intersector;
+pub mod keymap;
+pub mod matrix;
+pub mod triangulator;
+pub mod vec;
+pub mod view;
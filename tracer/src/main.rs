@@ -1,4 +1,6 @@
 use application::Program;
+use tracer::{annotation, formats, history, input, keymap, matrix, triangulator, vec, view};
+use ui_element::ui_root::Arguments;
 use window_context::WindowContext;
 
 extern crate glad_gl;
@@ -7,13 +9,16 @@ extern crate glfw;
 mod application;
 mod ui_element;
 mod window_context;
-mod intersector;
-mod vec;
 mod linked_node;
-mod triangulator;
 
 fn main() {
-    let mut program = Program::build("Final Solution", 1200, 800);
+    let arguments = match Arguments::parse(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(err) => {
+            panic!("Invalid arguments:\n{}", err.error_msg);
+        }
+    };
+    let mut program = Program::build("Final Solution", 1200, 800).with_arguments(arguments);
 
     let mut context = match WindowContext::build(&mut program) {
         Ok(context) => context,
@@ -0,0 +1,169 @@
+use std::ops;
+
+use crate::vec::Vec2;
+
+#[derive(Clone, Copy)]
+pub struct Matrix {
+    pub data: [[f32; 4]; 4],
+}
+
+impl Matrix {
+    pub fn ident() -> Self {
+        let mut data = [[0.0; 4]; 4];
+        (0..4).for_each(|i| data[i][i] = 1.0);
+
+        Self { data }
+    }
+
+    pub fn translate(x: f32, y: f32, z: f32) -> Self {
+        let mut mat = Self::ident();
+        mat.data[3][0] = x;
+        mat.data[3][1] = y;
+        mat.data[3][2] = z;
+        mat
+    }
+
+    pub fn scale(scale: f32) -> Self {
+        let mut mat = Self::ident();
+        (0..3).for_each(|i| mat.data[i][i] *= scale);
+        mat
+    }
+
+    /// Rotation about the z axis, counter-clockwise by `angle` radians.
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut mat = Self::ident();
+        mat.data[0][0] = cos;
+        mat.data[0][1] = sin;
+        mat.data[1][0] = -sin;
+        mat.data[1][1] = cos;
+        mat
+    }
+
+    /// Inverts the matrix by Gauss-Jordan elimination, `None` when it is
+    /// singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut data = self.data;
+        let mut inverse = Self::ident().data;
+        let magnitude = data
+            .iter()
+            .flatten()
+            .fold(0.0_f32, |max, value| max.max(value.abs()));
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|a, b| data[*a][column].abs().total_cmp(&data[*b][column].abs()))?;
+            if data[pivot][column].abs() <= f32::EPSILON * magnitude {
+                return None;
+            }
+            data.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let factor = 1.0 / data[column][column];
+            (0..4).for_each(|j| {
+                data[column][j] *= factor;
+                inverse[column][j] *= factor;
+            });
+            for row in (0..4).filter(|row| *row != column) {
+                let factor = data[row][column];
+                (0..4).for_each(|j| {
+                    data[row][j] -= factor * data[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                });
+            }
+        }
+        Some(Matrix { data: inverse })
+    }
+
+    /// Lengths the x and y axes end up with, which for rotations combined
+    /// with uniform scaling are both the scale.
+    pub fn axis_scale(&self) -> (f32, f32) {
+        let data = &self.data;
+        (data[0][0].hypot(data[0][1]), data[1][0].hypot(data[1][1]))
+    }
+}
+impl From<Matrix> for [[f32; 4]; 4] {
+    fn from(mat: Matrix) -> [[f32; 4]; 4] {
+        mat.data
+    }
+}
+
+impl ops::Mul<Matrix> for Matrix {
+    type Output = Matrix;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut result = [[0.0; 4]; 4];
+        for (i, column) in result.iter_mut().enumerate() {
+            for (j, cell) in column.iter_mut().enumerate() {
+                *cell = (0..4).fold(0.0, |a, k| a + self.data[i][k] * rhs.data[k][j]);
+            }
+        }
+        Matrix { data: result }
+    }
+}
+
+impl ops::Mul<Vec2> for Matrix {
+    type Output = Vec2;
+    fn mul(self, rhs: Vec2) -> Self::Output {
+        let data = &self.data;
+        Vec2::new((
+            rhs.x * data[0][0] + rhs.y * data[1][0] + data[3][0],
+            rhs.x * data[0][1] + rhs.y * data[1][1] + data[3][1],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: (f32, f32)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-5 && (actual.y - expected.1).abs() < 1e-5,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn rotates_counter_clockwise() {
+        let quarter = Matrix::rotate(std::f32::consts::FRAC_PI_2);
+        assert_close(quarter * Vec2::new((1.0, 0.0)), (0.0, 1.0));
+        assert_close(quarter * Vec2::new((0.0, 1.0)), (-1.0, 0.0));
+    }
+
+    #[test]
+    fn transforms_compose_left_to_right() {
+        let about_center = Matrix::translate(-1.0, -1.0, 0.0)
+            * Matrix::rotate(std::f32::consts::PI)
+            * Matrix::scale(2.0)
+            * Matrix::translate(1.0, 1.0, 0.0);
+
+        assert_close(about_center * Vec2::new((1.0, 1.0)), (1.0, 1.0));
+        assert_close(about_center * Vec2::new((2.0, 1.0)), (-1.0, 1.0));
+        assert_close(about_center * Vec2::new((1.0, 3.0)), (1.0, -3.0));
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let mut matrix =
+            Matrix::translate(3.0, -2.0, 0.5) * Matrix::rotate(0.7) * Matrix::scale(2.5);
+        matrix.data[1][0] += 0.3;
+        let identity = matrix * matrix.inverse().unwrap();
+
+        identity
+            .data
+            .iter()
+            .flatten()
+            .zip(Matrix::ident().data.iter().flatten())
+            .for_each(|(actual, expected)| assert!((actual - expected).abs() < 1e-5, "{}", actual));
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert!(Matrix::scale(0.0).inverse().is_none());
+        let mut projection = Matrix::ident();
+        projection.data[1] = projection.data[0];
+        assert!(projection.inverse().is_none());
+    }
+}
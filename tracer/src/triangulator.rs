@@ -1,86 +1,390 @@
-use std::iter::zip;
+use std::{collections::HashSet, iter::zip};
 
-use crate::{intersector::Intersector, vec::Vec2};
+use crate::{
+    intersector::Intersector,
+    vec::{predicates::orient2d, Vec2},
+};
+
+use self::incremental::CommittedOutline;
+
+mod delaunay;
+mod incremental;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriangulationMode {
+    EarClipping,
+    ConstrainedDelaunay,
+}
+
+/// Part of the mesh [`Triangulator::triangulate_incremental`] changed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshUpdate {
+    /// The mesh was rebuilt and may have changed size.
+    Full,
+    /// Only these vertices and triangles changed, the mesh kept its size.
+    Partial {
+        vertices: Vec<usize>,
+        triangles: Vec<usize>,
+    },
+}
 
 pub struct Triangulator {
+    mode: TriangulationMode,
     points: Vec<Vec2>,
+    holes: Vec<Vec<Vec2>>,
     vertices: Vec<Vec2>,
     indices: Vec<[u32; 3]>,
+    committed: Option<CommittedOutline>,
+    committed_stale: bool,
+    /// Whether the last incremental mesh came from `committed`, which later
+    /// updates then only change part of.
+    extending: bool,
+}
+
+struct Triangulation {
+    vertices: Vec<Vec2>,
+    indices: Vec<[u32; 3]>,
+    outlines: Vec<Vec<usize>>,
+    holes: Vec<Vec<usize>>,
+}
+
+impl Default for Triangulator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Triangulator {
     pub fn new() -> Self {
         Triangulator {
+            mode: TriangulationMode::EarClipping,
             points: vec![Vec2::new((0.0, 0.0))],
+            holes: vec![],
             vertices: vec![],
             indices: vec![],
+            committed: None,
+            committed_stale: true,
+            extending: false,
         }
     }
 
+    pub fn from_outline(outline: &[Vec2], holes: &[Vec<Vec2>]) -> Self {
+        let mut triangulator = Self::new();
+        outline.iter().for_each(|point| {
+            triangulator.add(*point);
+        });
+        holes.iter().for_each(|hole| {
+            triangulator.add_hole(hole.clone());
+        });
+        triangulator
+    }
+
+    pub fn with_mode(mut self, mode: TriangulationMode) -> Self {
+        self.set_mode(mode);
+        self
+    }
+
+    pub fn mode(&self) -> TriangulationMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TriangulationMode) {
+        self.mode = mode;
+        self.committed_stale = true;
+    }
+
     pub fn add(&mut self, point: Vec2) -> Option<()> {
         *self.points.last_mut()? = point;
         self.points.push(point);
+        self.committed_stale = true;
         Some(())
     }
 
     pub fn update(&mut self, pos: Vec2) -> Option<()> {
-        Some(*self.points.last_mut()? = pos)
+        *self.points.last_mut()? = pos;
+        Some(())
     }
 
-    fn compute_winding(&mut self) -> Option<f32> {
-        let origin = *self.points.first()?;
+    pub fn add_hole(&mut self, hole: Vec<Vec2>) -> Option<()> {
+        if hole.len() < 3 {
+            return None;
+        }
+        self.holes.push(hole);
+        self.committed_stale = true;
+        Some(())
+    }
+
+    /// Outline points without the trailing cursor point.
+    pub fn outline(&self) -> &[Vec2] {
+        &self.points[..self.points.len().saturating_sub(1)]
+    }
+
+    /// Moves a point of the outline, or of a hole when `hole` is given. Edits
+    /// of the outline pin the cursor back onto its last point.
+    pub fn move_point(&mut self, hole: Option<usize>, index: usize, point: Vec2) -> Option<()> {
+        let (ring, len) = self.ring_mut(hole)?;
+        if index >= len {
+            return None;
+        }
+        ring[index] = point;
+
+        let mode = self.mode;
+        let moved = match self.committed.as_mut() {
+            Some(committed) if !self.committed_stale => {
+                committed.move_point(hole, index, point, mode).is_some()
+            }
+            _ => false,
+        };
+        self.finish_edit()?;
+        self.committed_stale = !moved;
+        Some(())
+    }
+
+    pub fn insert_point(&mut self, hole: Option<usize>, index: usize, point: Vec2) -> Option<()> {
+        let (ring, len) = self.ring_mut(hole)?;
+        if index > len {
+            return None;
+        }
+        ring.insert(index, point);
+        self.finish_edit()
+    }
+
+    /// Removes a point unless that would leave its ring with less than three
+    /// points.
+    pub fn remove_point(&mut self, hole: Option<usize>, index: usize) -> Option<()> {
+        let (ring, len) = self.ring_mut(hole)?;
+        if index >= len || len <= 3 {
+            return None;
+        }
+        ring.remove(index);
+        self.finish_edit()
+    }
 
-        let winding = zip(self.points.iter().skip(1), self.points.iter().skip(2)).fold(
-            0.0,
-            |winding, (&v1, &v2)| {
+    /// The ring a point belongs to along with its length, which for the
+    /// outline leaves out the cursor kept at the end.
+    fn ring_mut(&mut self, hole: Option<usize>) -> Option<(&mut Vec<Vec2>, usize)> {
+        match hole {
+            Some(hole) => {
+                let ring = self.holes.get_mut(hole)?;
+                let len = ring.len();
+                Some((ring, len))
+            }
+            None => {
+                let len = self.points.len().checked_sub(1)?;
+                Some((&mut self.points, len))
+            }
+        }
+    }
+
+    fn finish_edit(&mut self) -> Option<()> {
+        if let [.., last, cursor] = self.points.as_mut_slice() {
+            *cursor = *last;
+        }
+        self.committed_stale = true;
+        Some(())
+    }
+
+    fn compute_winding(points: &[Vec2]) -> Option<f32> {
+        let origin = *points.first()?;
+
+        let winding =
+            zip(points.iter().skip(1), points.iter().skip(2)).fold(0.0, |winding, (&v1, &v2)| {
                 let v1 = v1 - origin;
                 let v2 = v2 - origin;
                 winding + v1.cross(v2)
-            },
-        );
+            });
         Some(winding)
     }
 
     pub fn triangulate(&mut self) -> Option<(&Vec<Vec2>, Vec<[u32; 3]>)> {
-        self.vertices.clear();
-        self.indices.clear();
-
-        //let intersector = Intersector::from_vertices(&self.points);
-        self.triangulate_convex()?;
-        self.vertices = self.points.clone();
+        let triangulation = self.triangulate_outline(&self.points)?;
+        self.vertices = triangulation.vertices;
+        self.indices = triangulation.indices;
+        self.extending = false;
         Some((&self.vertices, self.indices.clone()))
     }
 
+    /// Mesh of the last triangulation.
+    pub fn mesh(&self) -> (&[Vec2], &[[u32; 3]]) {
+        match &self.committed {
+            Some(committed) if self.extending => committed.mesh(),
+            _ => (&self.vertices, &self.indices),
+        }
+    }
+
+    /// Triangulates like [`triangulate`](Self::triangulate) while only
+    /// touching the triangles around whatever changed since the last call,
+    /// returning which part of [`mesh`](Self::mesh) that is. The cursor only
+    /// adds an ear onto the closing edge, or replaces the triangles that the
+    /// notch it cuts into the outline overlaps, and moving a point only
+    /// changes its position and, in Delaunay mode, flips the edges around it.
+    ///
+    /// The whole outline is triangulated again when
+    /// - points or holes were added, inserted or removed, or the mode changed,
+    ///   after which the update continues from the new triangulation,
+    /// - the outline crosses itself, holds the same point twice, or a hole
+    ///   lies outside of it, for as long as that lasts,
+    /// - the rubber band touches or crosses the outline, or lies along the
+    ///   closing edge,
+    /// - the triangles a notch overlaps do not form a simple polygon,
+    /// - a moved point lies on a run of collinear points, which leave gaps
+    ///   between the triangles, or it would turn one of its triangles over or
+    ///   make its ring touch itself or another ring.
+    pub fn triangulate_incremental(&mut self) -> Option<MeshUpdate> {
+        let cursor = *self.points.last()?;
+
+        if self.committed_stale {
+            let committed_points = self.outline();
+            let committed = self
+                .triangulate_outline(committed_points)
+                .and_then(|triangulation| {
+                    CommittedOutline::new(triangulation, committed_points, &self.holes)
+                });
+            self.committed = committed;
+            self.committed_stale = false;
+            self.extending = false;
+        }
+
+        let mode = self.mode;
+        match self
+            .committed
+            .as_mut()
+            .and_then(|committed| committed.extend(cursor, mode))
+        {
+            Some(update) if self.extending => Some(update),
+            Some(_) => {
+                self.extending = true;
+                Some(MeshUpdate::Full)
+            }
+            None => {
+                self.extending = false;
+                self.triangulate()?;
+                Some(MeshUpdate::Full)
+            }
+        }
+    }
+
+    fn triangulate_outline(&self, outline: &[Vec2]) -> Option<Triangulation> {
+        let mut triangulation = Triangulation {
+            vertices: vec![],
+            indices: vec![],
+            outlines: vec![],
+            holes: vec![],
+        };
+
+        let loops = Intersector::from_vertices(outline)?.get_closed_loops();
+        let holes: Vec<_> = self
+            .holes
+            .iter()
+            .filter_map(|hole| Intersector::from_vertices(hole))
+            .flat_map(|mut intersector| intersector.get_closed_loops())
+            .map(|mut hole| {
+                hole.reverse();
+                hole
+            })
+            .collect();
+
+        let vertices = &mut triangulation.vertices;
+        for outer in loops {
+            let mut contour: Vec<_> = (vertices.len()..vertices.len() + outer.len()).collect();
+            vertices.extend(outer.iter());
+
+            // The whole hole has to be inside, its rightmost point above all
+            // since that is where the bridge to the outline starts
+            let mut inner: Vec<Vec<usize>> = vec![];
+            for hole in holes.iter().filter(|hole| {
+                hole.iter()
+                    .all(|point| Self::polygon_contains(&outer, *point))
+            }) {
+                inner.push((vertices.len()..vertices.len() + hole.len()).collect());
+                vertices.extend(hole.iter());
+            }
+            inner.sort_by(|a, b| {
+                let (a, b) = (Self::rightmost(a, vertices), Self::rightmost(b, vertices));
+                vertices[b].x.total_cmp(&vertices[a].x)
+            });
+
+            triangulation.outlines.push(contour.clone());
+            // A hole the outline cannot be bridged to is dropped, its edges
+            // would otherwise constrain a mesh that does not hold them
+            inner.retain(|hole| Self::bridge_hole(&mut contour, hole, vertices).is_some());
+            let constraints: HashSet<_> = inner
+                .iter()
+                .chain(triangulation.outlines.last())
+                .flat_map(|ring| delaunay::ring_edges(ring))
+                .collect();
+            triangulation.holes.extend(inner);
+
+            let contour_points: Vec<_> = contour.iter().map(|i| vertices[*i]).collect();
+            if let Some(indices) = Self::triangulate_convex(&contour_points) {
+                let mut indices: Vec<_> = indices
+                    .iter()
+                    .map(|[p1, p2, p3]| {
+                        [
+                            contour[*p1 as usize] as u32,
+                            contour[*p2 as usize] as u32,
+                            contour[*p3 as usize] as u32,
+                        ]
+                    })
+                    .collect();
+
+                if self.mode == TriangulationMode::ConstrainedDelaunay {
+                    delaunay::flip_to_delaunay(vertices, &mut indices, &constraints);
+                }
+                triangulation.indices.extend(indices);
+            }
+        }
+        Some(triangulation)
+    }
+
+    pub fn area(vertices: &[Vec2], indices: &[[u32; 3]]) -> f32 {
+        indices.iter().fold(0.0, |area, [p1, p2, p3]| {
+            let (p1, p2, p3) = (
+                vertices[*p1 as usize],
+                vertices[*p2 as usize],
+                vertices[*p3 as usize],
+            );
+            area + ((p2 - p1).cross(p3 - p1) * 0.5).abs()
+        })
+    }
+
     pub fn get_points(&self) -> &Vec<Vec2> {
         &self.points
     }
 
-    pub fn triangulate_convex(&mut self) -> Option<()> {
-        if self.points.len() < 3 {
+    pub fn get_holes(&self) -> &Vec<Vec<Vec2>> {
+        &self.holes
+    }
+
+    pub fn triangulate_convex(loop_points: &[Vec2]) -> Option<Vec<[u32; 3]>> {
+        if loop_points.len() < 3 {
             return None;
         }
 
-        let winding = self.compute_winding()?;
+        let winding = Self::compute_winding(loop_points)?;
 
         let mut ears = vec![];
-        let mut points = self.points.clone();
+        let mut points = loop_points.to_vec();
 
         while points.len() >= 3 {
-            let point = (0..points.len()).find(|point| {
-                let triangle = Self::get_triangle(&points, *point);
-                let (s1, s2) = (triangle.0 - triangle.1, triangle.2 - triangle.1);
-                let cross = s1.cross(s2);
-                if cross * winding < 0.0 {
-                    !points
+            let ear = (0..points.len()).find_map(|point| {
+                let triangle = Self::get_triangle(&points, point);
+                let orientation = orient2d(triangle.0, triangle.1, triangle.2);
+                if orientation == 0.0 {
+                    Some((point, false))
+                } else if (orientation > 0.0) == (winding > 0.0)
+                    && !points
                         .iter()
                         .any(|point| Self::triangle_contains(triangle, *point))
+                {
+                    Some((point, true))
                 } else {
-                    false
+                    None
                 }
             });
-            match point {
-                Some(point) => {
-                    ears.push(point);
+            match ear {
+                Some((point, is_triangle)) => {
+                    ears.push((point, is_triangle));
                     points.remove(point);
                 }
                 None => {
@@ -89,33 +393,120 @@ impl Triangulator {
             }
         }
 
-        let mut mesh_indices: Vec<_> = (0..self.points.len()).collect();
+        let mut mesh_indices: Vec<_> = (0..loop_points.len()).collect();
+        let mut indices = vec![];
 
-        ears.iter().for_each(|i| {
-            let (p1, p2, p3) = Self::get_triangle(&mesh_indices, *i);
-            self.indices.push([p1 as u32, p2 as u32, p3 as u32]);
+        ears.iter().for_each(|(i, is_triangle)| {
+            if *is_triangle {
+                let (p1, p2, p3) = Self::get_triangle(&mesh_indices, *i);
+                indices.push([p1 as u32, p2 as u32, p3 as u32]);
+            }
             mesh_indices.remove(*i);
         });
 
+        Some(indices)
+    }
+
+    fn bridge_hole(contour: &mut Vec<usize>, hole: &[usize], vertices: &[Vec2]) -> Option<()> {
+        let rightmost = Self::rightmost(hole, vertices);
+        let hole_point = vertices[rightmost];
+
+        let mut closest: Option<(usize, f32)> = None;
+        for i in 0..contour.len() {
+            let (start, end) = (
+                vertices[contour[i]],
+                vertices[contour[(i + 1) % contour.len()]],
+            );
+            if start.y == end.y
+                || hole_point.y < start.y.min(end.y)
+                || hole_point.y > start.y.max(end.y)
+            {
+                continue;
+            }
+
+            let x = start.x + (hole_point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+            if x >= hole_point.x && closest.is_none_or(|(_, closest_x)| x < closest_x) {
+                let i = if start.x > end.x {
+                    i
+                } else {
+                    (i + 1) % contour.len()
+                };
+                closest = Some((i, x));
+            }
+        }
+
+        let (mut bridge, x) = closest?;
+        let intersection = Vec2::new((x, hole_point.y));
+        let triangle = (hole_point, intersection, vertices[contour[bridge]]);
+
+        // Of the points inside the triangle the one at the smallest angle to
+        // the ray is visible from the hole, angles are compared through
+        // cross products of directions pointing right
+        let mut best: Option<Vec2> = None;
+        for (i, point) in contour.iter().map(|i| vertices[*i]).enumerate() {
+            let direction = point - hole_point;
+            if direction.x <= 0.0 || !Self::triangle_contains(triangle, point) {
+                continue;
+            }
+            if best.is_none_or(|best| direction.y.abs() * best.x < best.y.abs() * direction.x) {
+                best = Some(direction);
+                bridge = i;
+            }
+        }
+
+        let hole_start = hole.iter().position(|i| *i == rightmost)?;
+        let mut bridged: Vec<_> = contour[..=bridge].to_vec();
+        bridged.extend(hole[hole_start..].iter());
+        bridged.extend(hole[..=hole_start].iter());
+        bridged.extend(contour[bridge..].iter());
+
+        *contour = bridged;
         Some(())
     }
 
+    fn rightmost(polygon: &[usize], vertices: &[Vec2]) -> usize {
+        polygon.iter().fold(polygon[0], |rightmost, i| {
+            if vertices[*i].x > vertices[rightmost].x {
+                *i
+            } else {
+                rightmost
+            }
+        })
+    }
+
+    /// Even-odd test of the point against the closed polygon.
+    pub fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+        (0..polygon.len()).fold(false, |inside, i| {
+            let (start, end) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            if (start.y > point.y) != (end.y > point.y)
+                && point.x < start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x)
+            {
+                !inside
+            } else {
+                inside
+            }
+        })
+    }
+
     fn triangle_contains(triangle: (Vec2, Vec2, Vec2), point: Vec2) -> bool {
-        let normals = (
-            (triangle.0 - triangle.1).perp(),
-            (triangle.1 - triangle.2).perp(),
-            (triangle.2 - triangle.0).perp(),
-        );
+        if orient2d(triangle.0, triangle.1, triangle.2) == 0.0
+            || [triangle.0, triangle.1, triangle.2]
+                .iter()
+                .any(|vertex| vertex.x == point.x && vertex.y == point.y)
+        {
+            return false;
+        }
+
         let signs = (
-            (point - triangle.0) * normals.0,
-            (point - triangle.1) * normals.1,
-            (point - triangle.2) * normals.2,
+            orient2d(triangle.0, triangle.1, point),
+            orient2d(triangle.1, triangle.2, point),
+            orient2d(triangle.2, triangle.0, point),
         );
-        (signs.0 < 0.0 && signs.1 < 0.0 && signs.2 < 0.0)
-            || (signs.0 > 0.0 && signs.1 > 0.0 && signs.2 > 0.0)
+        (signs.0 <= 0.0 && signs.1 <= 0.0 && signs.2 <= 0.0)
+            || (signs.0 >= 0.0 && signs.1 >= 0.0 && signs.2 >= 0.0)
     }
 
-    fn get_triangle<T: Copy>(vertices: &Vec<T>, i: usize) -> (T, T, T) {
+    fn get_triangle<T: Copy>(vertices: &[T], i: usize) -> (T, T, T) {
         let length = vertices.len();
         (
             vertices[(i + length - 1) % length],
@@ -124,3 +515,343 @@ impl Triangulator {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::annotation::test_support::square;
+    use proptest::prelude::*;
+
+    fn from_outline(outline: &[Vec2]) -> Triangulator {
+        Triangulator::from_outline(outline, &[])
+    }
+
+    #[test]
+    fn figure_eight_fills_both_lobes() {
+        let mut triangulator = from_outline(&[
+            Vec2::new((0.0, 0.0)),
+            Vec2::new((2.0, 2.0)),
+            Vec2::new((2.0, 0.0)),
+            Vec2::new((0.0, 2.0)),
+        ]);
+        let (vertices, indices) = triangulator.triangulate().unwrap();
+
+        assert_eq!(indices.len(), 2);
+        assert_eq!(Triangulator::area(vertices, &indices), 2.0);
+    }
+
+    #[test]
+    fn holes_are_left_uncovered() {
+        let mut triangulator = from_outline(&square(0.0, 0.0, 10.0));
+        triangulator.add_hole(square(2.0, 2.0, 2.0));
+        triangulator.add_hole(square(6.0, 5.0, 3.0));
+        let (vertices, indices) = triangulator.triangulate().unwrap();
+
+        assert_eq!(Triangulator::area(vertices, &indices), 100.0 - 4.0 - 9.0);
+    }
+
+    #[test]
+    fn both_modes_leave_holes_uncovered() {
+        for mode in [
+            TriangulationMode::EarClipping,
+            TriangulationMode::ConstrainedDelaunay,
+        ] {
+            let mut triangulator = from_outline(&square(0.0, 0.0, 10.0)).with_mode(mode);
+            triangulator.add_hole(square(1.0, 1.0, 3.0));
+            triangulator.add_hole(square(5.0, 6.0, 2.0));
+            let (vertices, indices) = triangulator.triangulate().unwrap();
+
+            assert_eq!(
+                Triangulator::area(vertices, &indices),
+                100.0 - 9.0 - 4.0,
+                "Invalid area for: {:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn holes_sticking_out_of_the_outline_are_dropped() {
+        for mode in [
+            TriangulationMode::EarClipping,
+            TriangulationMode::ConstrainedDelaunay,
+        ] {
+            let mut full = from_outline(&square(0.0, 0.0, 10.0)).with_mode(mode);
+            full.add_hole(square(8.0, 2.0, 4.0));
+            let mut incremental = from_outline(&square(0.0, 0.0, 10.0)).with_mode(mode);
+            incremental.add_hole(square(8.0, 2.0, 4.0));
+
+            let (vertices, indices) = full.triangulate().unwrap();
+            assert_eq!(Triangulator::area(vertices, &indices), 100.0);
+            incremental.triangulate_incremental().unwrap();
+            let (vertices, indices) = incremental.mesh();
+            assert_eq!(Triangulator::area(vertices, indices), 100.0);
+        }
+    }
+
+    #[test]
+    fn editing_points_retriangulates() {
+        let mut triangulator = from_outline(&square(0.0, 0.0, 10.0));
+        triangulator.add_hole(square(2.0, 2.0, 2.0));
+
+        triangulator.move_point(None, 2, Vec2::new((20.0, 10.0)));
+        triangulator.insert_point(None, 4, Vec2::new((-10.0, 5.0)));
+        triangulator.move_point(Some(0), 0, Vec2::new((1.0, 1.0)));
+        assert_eq!(triangulator.outline().len(), 5);
+        assert_eq!(
+            triangulator.get_points().last(),
+            Some(&Vec2::new((-10.0, 5.0)))
+        );
+
+        triangulator.triangulate_incremental().unwrap();
+        let (vertices, indices) = triangulator.mesh();
+        assert_eq!(Triangulator::area(vertices, indices), 150.0 + 50.0 - 6.0);
+
+        assert!(triangulator.remove_point(None, 4).is_some());
+        assert!(triangulator.remove_point(Some(0), 1).is_some());
+        assert!(triangulator.remove_point(Some(0), 1).is_none());
+        assert!(triangulator
+            .insert_point(None, 6, Vec2::new((0.0, 0.0)))
+            .is_none());
+        triangulator.triangulate_incremental().unwrap();
+        let (vertices, indices) = triangulator.mesh();
+        assert_eq!(Triangulator::area(vertices, indices), 150.0 - 3.0);
+    }
+
+    fn partial(vertices: &[usize], triangles: &[usize]) -> Option<MeshUpdate> {
+        Some(MeshUpdate::Partial {
+            vertices: vertices.to_vec(),
+            triangles: triangles.to_vec(),
+        })
+    }
+
+    #[test]
+    fn cursor_only_updates_the_triangles_next_to_it() {
+        let mut triangulator = from_outline(&square(0.0, 0.0, 10.0));
+        assert_eq!(
+            triangulator.triangulate_incremental(),
+            Some(MeshUpdate::Full)
+        );
+
+        triangulator.update(Vec2::new((-5.0, 5.0)));
+        assert_eq!(triangulator.triangulate_incremental(), partial(&[4], &[2]));
+        let (vertices, indices) = triangulator.mesh();
+        assert_eq!(Triangulator::area(vertices, indices), 125.0);
+
+        triangulator.update(Vec2::new((5.0, 8.0)));
+        let Some(MeshUpdate::Partial { vertices, .. }) = triangulator.triangulate_incremental()
+        else {
+            panic!("notch was not cut into the committed triangles");
+        };
+        assert_eq!(vertices, [4]);
+        let (vertices, indices) = triangulator.mesh();
+        assert_eq!(Triangulator::area(vertices, indices), 75.0);
+
+        triangulator.update(Vec2::new((20.0, 5.0)));
+        assert_eq!(
+            triangulator.triangulate_incremental(),
+            Some(MeshUpdate::Full)
+        );
+    }
+
+    #[test]
+    fn moving_points_keeps_the_triangulation() {
+        let mut triangulator =
+            from_outline(&square(0.0, 0.0, 10.0)).with_mode(TriangulationMode::ConstrainedDelaunay);
+        triangulator.add_hole(square(2.0, 2.0, 2.0));
+        triangulator.update(Vec2::new((0.0, 10.0)));
+        assert_eq!(
+            triangulator.triangulate_incremental(),
+            Some(MeshUpdate::Full)
+        );
+
+        triangulator.move_point(Some(0), 2, Vec2::new((5.0, 5.0)));
+        let Some(MeshUpdate::Partial { vertices, .. }) = triangulator.triangulate_incremental()
+        else {
+            panic!("moving a point triangulated the outline again");
+        };
+        assert_eq!(vertices.len(), 2);
+        let (vertices, indices) = triangulator.mesh();
+        assert_eq!(Triangulator::area(vertices, indices), 100.0 - 6.0);
+
+        triangulator.move_point(Some(0), 2, Vec2::new((15.0, 5.0)));
+        assert_eq!(
+            triangulator.triangulate_incremental(),
+            Some(MeshUpdate::Full)
+        );
+    }
+
+    #[test]
+    fn holes_outside_of_outline_are_ignored() {
+        let mut triangulator = from_outline(&square(0.0, 0.0, 10.0));
+        triangulator.add_hole(square(20.0, 2.0, 2.0));
+        let (vertices, indices) = triangulator.triangulate().unwrap();
+
+        assert_eq!(Triangulator::area(vertices, &indices), 100.0);
+    }
+
+    fn star_polygon(points: Vec<(i32, i32)>) -> Vec<Vec2> {
+        let mut points: Vec<_> = points
+            .into_iter()
+            .filter(|point| *point != (0, 0))
+            .map(|(x, y)| Vec2::new((x as f32 * 2.0, y as f32 * 2.0)))
+            .collect();
+        points.sort_by(|a, b| a.y.atan2(a.x).total_cmp(&b.y.atan2(b.x)));
+        points.dedup_by(|a, b| orient2d(Vec2::new((0.0, 0.0)), *a, *b) == 0.0);
+
+        let is_star_shaped = (0..points.len()).all(|i| {
+            let next = points[(i + 1) % points.len()];
+            orient2d(Vec2::new((0.0, 0.0)), points[i], next) > 0.0
+        });
+        if is_star_shaped {
+            points
+        } else {
+            vec![]
+        }
+    }
+
+    fn polygon_area(polygon: &[Vec2]) -> f32 {
+        Intersector::signed_area(polygon).abs()
+    }
+
+    proptest! {
+        #[test]
+        fn collinear_and_duplicate_points_keep_area(
+            points in proptest::collection::vec((-32..32, -32..32), 3..40),
+            midpoints in proptest::collection::vec(any::<bool>(), 40),
+            duplicates in proptest::collection::vec(any::<bool>(), 40),
+        ) {
+            let polygon = star_polygon(points);
+            prop_assume!(polygon.len() >= 3);
+            let mut outline = vec![];
+            for (i, point) in polygon.iter().enumerate() {
+                let next = polygon[(i + 1) % polygon.len()];
+                outline.push(*point);
+                if duplicates[i] {
+                    outline.push(*point);
+                }
+                if midpoints[i] {
+                    outline.push(Vec2::new(((point.x + next.x) * 0.5, (point.y + next.y) * 0.5)));
+                }
+            }
+
+            let mut triangulator = from_outline(&outline);
+            let (vertices, indices) = triangulator.triangulate().unwrap();
+
+            for [p1, p2, p3] in &indices {
+                let (p1, p2, p3) = (
+                    vertices[*p1 as usize],
+                    vertices[*p2 as usize],
+                    vertices[*p3 as usize],
+                );
+                prop_assert!(orient2d(p1, p2, p3) > 0.0);
+            }
+            prop_assert_eq!(Triangulator::area(vertices, &indices), polygon_area(&polygon));
+        }
+
+        #[test]
+        fn both_modes_cover_polygon_area(
+            points in proptest::collection::vec((-32..32, -32..32), 3..40),
+        ) {
+            let polygon = star_polygon(points);
+            prop_assume!(polygon.len() >= 3);
+
+            let mut ear_clipping = from_outline(&polygon);
+            let mut delaunay =
+                from_outline(&polygon).with_mode(TriangulationMode::ConstrainedDelaunay);
+
+            let (vertices, indices) = ear_clipping.triangulate().unwrap();
+            let ear_clipping_area = Triangulator::area(vertices, &indices);
+            let (vertices, indices) = delaunay.triangulate().unwrap();
+            let delaunay_area = Triangulator::area(vertices, &indices);
+
+            prop_assert_eq!(ear_clipping_area, delaunay_area);
+            prop_assert_eq!(delaunay_area, polygon_area(&polygon));
+        }
+
+        #[test]
+        fn incremental_matches_full_triangulation(
+            points in proptest::collection::vec((-32..32, -32..32), 3..40),
+            cursors in proptest::collection::vec(
+                proptest::collection::vec((-80..80, -80..80), 1..4),
+                40,
+            ),
+            moves in proptest::collection::vec((0..40usize, 1..5), 0..8),
+            delaunay in any::<bool>(),
+        ) {
+            let polygon = star_polygon(points);
+            prop_assume!(polygon.len() >= 3);
+
+            let mode = if delaunay {
+                TriangulationMode::ConstrainedDelaunay
+            } else {
+                TriangulationMode::EarClipping
+            };
+            let mut incremental = Triangulator::new().with_mode(mode);
+            let mut full = Triangulator::new().with_mode(mode);
+            let mut uploaded = (vec![], vec![]);
+
+            let mut check = |incremental: &mut Triangulator, full: &mut Triangulator| {
+                match incremental.triangulate_incremental() {
+                    Some(MeshUpdate::Full) => {
+                        let (vertices, indices) = incremental.mesh();
+                        uploaded = (vertices.to_vec(), indices.to_vec());
+                    }
+                    Some(MeshUpdate::Partial { vertices, triangles }) => {
+                        let mesh = incremental.mesh();
+                        vertices.iter().for_each(|i| uploaded.0[*i] = mesh.0[*i]);
+                        triangles.iter().for_each(|i| uploaded.1[*i] = mesh.1[*i]);
+                    }
+                    None => {}
+                }
+                let (vertices, indices) = incremental.mesh();
+                prop_assert!(
+                    vertices.iter().all(|v| v.x.is_finite() && v.y.is_finite()),
+                    "{:?}",
+                    vertices
+                );
+                prop_assert_eq!(&uploaded.0[..], vertices);
+                prop_assert_eq!(&uploaded.1[..], indices);
+
+                let incremental_area = Triangulator::area(vertices, indices);
+                let full_area = full
+                    .triangulate()
+                    .map(|(vertices, indices)| Triangulator::area(vertices, &indices))
+                    .unwrap_or(0.0);
+                prop_assert!(
+                    (incremental_area - full_area).abs() <= full_area * 1e-5,
+                    "{} != {}",
+                    incremental_area,
+                    full_area
+                );
+                Ok(())
+            };
+
+            for (point, cursors) in polygon.iter().zip(cursors) {
+                for triangulator in [&mut incremental, &mut full] {
+                    triangulator.add(*point);
+                }
+                for (x, y) in cursors {
+                    let cursor = Vec2::new((x as f32, y as f32));
+                    for triangulator in [&mut incremental, &mut full] {
+                        triangulator.update(cursor);
+                    }
+                    check(&mut incremental, &mut full)?;
+                }
+            }
+
+            // Scaling points away from or towards the origin keeps the
+            // polygon star-shaped
+            for (index, scale) in moves {
+                let index = index % polygon.len();
+                let scale = scale as f32 * 0.5;
+                let point = incremental.outline()[index];
+                let point = Vec2::new((point.x * scale, point.y * scale));
+                for triangulator in [&mut incremental, &mut full] {
+                    triangulator.move_point(None, index, point);
+                }
+                check(&mut incremental, &mut full)?;
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::vec::{
+    predicates::{incircle, orient2d},
+    Vec2,
+};
+
+pub type Edge = (u32, u32);
+
+pub fn edge_key((a, b): Edge) -> Edge {
+    (a.min(b), a.max(b))
+}
+
+pub fn ring_edges(ring: &[usize]) -> impl Iterator<Item = Edge> + '_ {
+    (0..ring.len()).map(|i| edge_key((ring[i] as u32, ring[(i + 1) % ring.len()] as u32)))
+}
+
+pub type Adjacency = HashMap<Edge, Vec<usize>>;
+
+/// Triangles next to every edge of a triangulation.
+pub fn adjacency(triangles: &[[u32; 3]]) -> Adjacency {
+    let mut adjacency = Adjacency::new();
+    for (i, triangle) in triangles.iter().enumerate() {
+        add_triangle(&mut adjacency, triangle, i);
+    }
+    adjacency
+}
+
+pub fn add_triangle(adjacency: &mut Adjacency, triangle: &[u32; 3], i: usize) {
+    for edge in triangle_edges(triangle) {
+        adjacency.entry(edge_key(edge)).or_default().push(i);
+    }
+}
+
+pub fn remove_triangle(adjacency: &mut Adjacency, triangle: &[u32; 3], i: usize) {
+    for edge in triangle_edges(triangle) {
+        let edge = edge_key(edge);
+        if let Some(adjacent) = adjacency.get_mut(&edge) {
+            adjacent.retain(|triangle| *triangle != i);
+            if adjacent.is_empty() {
+                adjacency.remove(&edge);
+            }
+        }
+    }
+}
+
+/// Flips unconstrained edges of a counter-clockwise triangulation until every
+/// edge satisfies the empty circumcircle property (Lawson's algorithm), which
+/// turns any triangulation of a polygon into its constrained Delaunay one.
+pub fn flip_to_delaunay(
+    vertices: &[Vec2],
+    triangles: &mut [[u32; 3]],
+    constraints: &HashSet<Edge>,
+) {
+    let mut adjacency = adjacency(triangles);
+    let pending: Vec<_> = adjacency.keys().copied().collect();
+    flip_edges(vertices, triangles, &mut adjacency, pending, |edge| {
+        constraints.contains(&edge)
+    });
+}
+
+/// Runs Lawson's algorithm from the `pending` edges only, for triangulations
+/// that are Delaunay everywhere else. Flips spread to the edges around the
+/// flipped ones, so they stay within the region the Delaunay property was
+/// broken in. Returns the triangles that were flipped.
+pub fn flip_edges(
+    vertices: &[Vec2],
+    triangles: &mut [[u32; 3]],
+    adjacency: &mut Adjacency,
+    mut pending: Vec<Edge>,
+    is_constraint: impl Fn(Edge) -> bool,
+) -> Vec<usize> {
+    pending.retain(|edge| !is_constraint(*edge));
+    let mut flipped = vec![];
+
+    // Lawson flips always terminate with exact predicates, the bound only
+    // guards against a malformed input triangulation
+    let max_flips = triangles.len() * triangles.len() + pending.len();
+    let mut flips = 0;
+
+    while let Some(edge) = pending.pop() {
+        let Some(&[t1, t2]) = adjacency.get(&edge).map(|adjacent| adjacent.as_slice()) else {
+            continue;
+        };
+        let Some((a, b, c)) = opposite_vertex(&triangles[t1], edge) else {
+            continue;
+        };
+        let Some((_, _, d)) = opposite_vertex(&triangles[t2], edge) else {
+            continue;
+        };
+
+        let point = |i: u32| vertices[i as usize];
+        if incircle(point(a), point(b), point(c), point(d)) <= 0.0
+            || orient2d(point(a), point(d), point(c)) <= 0.0
+            || orient2d(point(d), point(b), point(c)) <= 0.0
+        {
+            continue;
+        }
+
+        triangles[t1] = [a, d, c];
+        triangles[t2] = [d, b, c];
+        flipped.extend([t1, t2]);
+
+        adjacency.remove(&edge);
+        adjacency.insert(edge_key((c, d)), vec![t1, t2]);
+        replace_triangle(adjacency, (b, c), t1, t2);
+        replace_triangle(adjacency, (a, d), t2, t1);
+
+        pending.extend(
+            [(a, d), (d, b), (b, c), (c, a)]
+                .into_iter()
+                .map(edge_key)
+                .filter(|edge| !is_constraint(*edge)),
+        );
+
+        flips += 1;
+        if flips > max_flips {
+            break;
+        }
+    }
+    flipped
+}
+
+pub fn triangle_edges(triangle: &[u32; 3]) -> [Edge; 3] {
+    [
+        (triangle[0], triangle[1]),
+        (triangle[1], triangle[2]),
+        (triangle[2], triangle[0]),
+    ]
+}
+
+fn opposite_vertex(triangle: &[u32; 3], edge: Edge) -> Option<(u32, u32, u32)> {
+    (0..3)
+        .map(|i| (triangle[i], triangle[(i + 1) % 3], triangle[(i + 2) % 3]))
+        .find(|(a, b, _)| edge_key((*a, *b)) == edge)
+}
+
+fn replace_triangle(adjacency: &mut Adjacency, edge: Edge, from: usize, to: usize) {
+    if let Some(adjacent) = adjacency.get_mut(&edge_key(edge)) {
+        adjacent
+            .iter_mut()
+            .filter(|triangle| **triangle == from)
+            .for_each(|triangle| *triangle = to);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fan_of_convex_polygon_becomes_delaunay() {
+        let vertices: Vec<_> = (0..12)
+            .map(|i| {
+                let angle = i as f32 / 12.0 * std::f32::consts::TAU;
+                Vec2::new((angle.cos() * 4.0, angle.sin()))
+            })
+            .collect();
+        let ring: Vec<_> = (0..vertices.len()).collect();
+        let constraints: HashSet<_> = ring_edges(&ring).collect();
+
+        let mut triangles: Vec<_> = (1..vertices.len() as u32 - 1)
+            .map(|i| [0, i, i + 1])
+            .collect();
+        flip_to_delaunay(&vertices, &mut triangles, &constraints);
+
+        for (i, first) in triangles.iter().enumerate() {
+            for second in &triangles[i + 1..] {
+                for edge in triangle_edges(first) {
+                    let edge = edge_key(edge);
+                    let (Some((a, b, c)), Some((_, _, d))) =
+                        (opposite_vertex(first, edge), opposite_vertex(second, edge))
+                    else {
+                        continue;
+                    };
+                    let point = |i: u32| vertices[i as usize];
+                    assert!(incircle(point(a), point(b), point(c), point(d)) <= 0.0);
+                }
+            }
+        }
+
+        for triangle in &triangles {
+            let point = |i: u32| vertices[i as usize];
+            assert!(orient2d(point(triangle[0]), point(triangle[1]), point(triangle[2])) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use crate::{
+    intersector::{Intersector, Segment},
+    vec::{predicates::orient2d, Vec2},
+};
+
+use super::{
+    delaunay::{self, Adjacency, Edge},
+    MeshUpdate, Triangulation, TriangulationMode, Triangulator,
+};
+
+/// Triangulation of every point except the rubber-band cursor, along with the
+/// mesh that extends it by the cursor. Moving the cursor only replaces the
+/// triangles it cuts into or adds next to the closing edge, and moving a point
+/// only the triangles around it, so neither triangulates the whole outline
+/// again.
+pub struct CommittedOutline {
+    /// Committed vertices followed by the cursor.
+    vertices: Vec<Vec2>,
+    /// Committed triangles followed by the slot of the cursor's ear, which
+    /// holds a degenerate triangle while there is no ear.
+    triangles: Vec<[u32; 3]>,
+    /// Triangles without the cursor, which every update starts over from.
+    committed: Vec<[u32; 3]>,
+    /// Triangles next to every edge of `triangles`.
+    adjacency: Adjacency,
+    constraints: HashSet<Edge>,
+    /// The outline followed by the holes, counter-clockwise and clockwise.
+    rings: Vec<Vec<usize>>,
+    /// Ring of every committed vertex and its position in the ring.
+    ring_positions: Vec<(usize, usize)>,
+    /// Vertex of every outline point and of every point of each hole.
+    outline_vertices: Vec<usize>,
+    hole_vertices: Vec<Vec<usize>>,
+    closing_edge: (usize, usize),
+    /// Slots of `triangles` that the cursor changed from `committed`.
+    extended: Vec<usize>,
+    changed_vertices: Vec<usize>,
+    changed_triangles: Vec<usize>,
+}
+
+impl CommittedOutline {
+    /// Requires every point of the outline and holes to end up as a vertex of
+    /// its own, which rules out self-intersecting outlines, duplicate points
+    /// and holes that were dropped.
+    pub fn new(triangulation: Triangulation, points: &[Vec2], holes: &[Vec<Vec2>]) -> Option<Self> {
+        let Triangulation {
+            mut vertices,
+            indices,
+            outlines,
+            holes: hole_rings,
+        } = triangulation;
+        if outlines.len() != 1
+            || vertices.len() != points.len() + holes.iter().map(Vec::len).sum::<usize>()
+        {
+            return None;
+        }
+
+        let key = |point: &Vec2| (point.x.to_bits(), point.y.to_bits());
+        let mut lookup = HashMap::new();
+        for (i, vertex) in vertices.iter().enumerate() {
+            if lookup.insert(key(vertex), i).is_some() {
+                return None;
+            }
+        }
+        let ring_vertices = |ring: &[Vec2]| -> Option<Vec<_>> {
+            ring.iter()
+                .map(|point| lookup.get(&key(point)).copied())
+                .collect()
+        };
+        let outline_vertices = ring_vertices(points)?;
+        let hole_vertices = holes
+            .iter()
+            .map(|hole| ring_vertices(hole))
+            .collect::<Option<Vec<_>>>()?;
+
+        let rings: Vec<_> = outlines.into_iter().chain(hole_rings).collect();
+        let mut ring_positions = vec![(0, 0); vertices.len()];
+        for (ring, vertices) in rings.iter().enumerate() {
+            for (position, vertex) in vertices.iter().enumerate() {
+                ring_positions[*vertex] = (ring, position);
+            }
+        }
+
+        let (first, last) = (*outline_vertices.first()?, *outline_vertices.last()?);
+        let outline = &rings[0];
+        let next = |vertex: usize| outline[(ring_positions[vertex].1 + 1) % outline.len()];
+        let closing_edge = if next(last) == first {
+            (last, first)
+        } else if next(first) == last {
+            (first, last)
+        } else {
+            return None;
+        };
+
+        let constraints = rings
+            .iter()
+            .flat_map(|ring| delaunay::ring_edges(ring))
+            .collect();
+        let adjacency = delaunay::adjacency(&indices);
+
+        let cursor = vertices.len();
+        vertices.push(vertices[closing_edge.0]);
+        let mut triangles = indices.clone();
+        triangles.push(Self::no_ear(closing_edge));
+
+        Some(CommittedOutline {
+            vertices,
+            triangles,
+            committed: indices,
+            adjacency,
+            constraints,
+            rings,
+            ring_positions,
+            outline_vertices,
+            hole_vertices,
+            closing_edge,
+            extended: vec![],
+            changed_vertices: vec![cursor],
+            changed_triangles: vec![],
+        })
+    }
+
+    pub fn mesh(&self) -> (&[Vec2], &[[u32; 3]]) {
+        (&self.vertices, &self.triangles)
+    }
+
+    /// Places the cursor and triangulates the ear it adds onto the closing
+    /// edge, or the notch it cuts into the outline from inside of it. Returns
+    /// what changed since the last update, or `None` when the rubber band
+    /// touches the outline or the notch does not cut into a simple part of
+    /// the mesh, which the full triangulation has to handle.
+    pub fn extend(&mut self, cursor: Vec2, mode: TriangulationMode) -> Option<MeshUpdate> {
+        let extended = self.try_extend(cursor, mode);
+        let changes = self.take_changes();
+        extended.map(|_| changes)
+    }
+
+    fn try_extend(&mut self, cursor: Vec2, mode: TriangulationMode) -> Option<()> {
+        self.restore();
+        let cursor_index = self.vertices.len() - 1;
+        self.vertices[cursor_index] = cursor;
+        self.changed_vertices.push(cursor_index);
+
+        let (start, end) = self.closing_edge;
+        let (start_point, end_point) = (self.vertices[start], self.vertices[end]);
+        let is_endpoint = [start_point, end_point]
+            .iter()
+            .any(|end| end.x == cursor.x && end.y == cursor.y);
+        if is_endpoint {
+            return Some(());
+        }
+
+        let orientation = orient2d(start_point, end_point, cursor);
+        if orientation == 0.0 || self.overlaps(cursor) {
+            return None;
+        }
+
+        let cursor_index = cursor_index as u32;
+        let ear_slot = self.committed.len();
+        let (slots, triangles) = if orientation < 0.0 {
+            (
+                vec![ear_slot],
+                vec![[start as u32, cursor_index, end as u32]],
+            )
+        } else {
+            let mut cavity = self.cavity([start_point, end_point, cursor])?;
+            let mut ring = self.boundary(&cavity)?;
+            ring.push(cursor_index);
+
+            let points: Vec<_> = ring.iter().map(|i| self.vertices[*i as usize]).collect();
+            let triangles: Vec<_> = Triangulator::triangulate_convex(&points)?
+                .iter()
+                .map(|triangle| triangle.map(|i| ring[i as usize]))
+                .collect();
+            cavity.push(ear_slot);
+            if triangles.len() != cavity.len() || !triangles.iter().all(|t| self.is_ccw(t)) {
+                return None;
+            }
+            (cavity, triangles)
+        };
+
+        for (slot, triangle) in slots.iter().zip(&triangles) {
+            delaunay::remove_triangle(&mut self.adjacency, &self.triangles[*slot], *slot);
+            self.triangles[*slot] = *triangle;
+            delaunay::add_triangle(&mut self.adjacency, triangle, *slot);
+        }
+        self.extended.extend(&slots);
+        self.changed_triangles.extend(&slots);
+
+        if mode == TriangulationMode::ConstrainedDelaunay {
+            let closing_edge = delaunay::edge_key((start as u32, end as u32));
+            let rubber_band = [
+                delaunay::edge_key((start as u32, cursor_index)),
+                delaunay::edge_key((cursor_index, end as u32)),
+            ];
+            let constraints = &self.constraints;
+            let flipped = delaunay::flip_edges(
+                &self.vertices,
+                &mut self.triangles,
+                &mut self.adjacency,
+                triangles
+                    .iter()
+                    .flat_map(delaunay::triangle_edges)
+                    .map(delaunay::edge_key)
+                    .collect(),
+                |edge| {
+                    (constraints.contains(&edge) && edge != closing_edge)
+                        || rubber_band.contains(&edge)
+                },
+            );
+            self.extended.extend(&flipped);
+            self.changed_triangles.extend(&flipped);
+        }
+        Some(())
+    }
+
+    /// Moves a committed point, of the outline or of a hole when `hole` is
+    /// given, and flips the edges around it back to Delaunay. Fails when the
+    /// point is not known, borders a gap the triangles left, or the move
+    /// would turn one of its triangles over or make its ring touch itself or
+    /// another ring.
+    pub fn move_point(
+        &mut self,
+        hole: Option<usize>,
+        index: usize,
+        point: Vec2,
+        mode: TriangulationMode,
+    ) -> Option<()> {
+        let vertex = *match hole {
+            Some(hole) => self.hole_vertices.get(hole)?.get(index)?,
+            None => self.outline_vertices.get(index)?,
+        };
+        self.restore();
+
+        let star: Vec<_> = (0..self.committed.len())
+            .filter(|slot| self.committed[*slot].contains(&(vertex as u32)))
+            .collect();
+
+        let previous = mem::replace(&mut self.vertices[vertex], point);
+        if !self.is_surrounded(vertex, &star)
+            || !star.iter().all(|slot| self.is_ccw(&self.committed[*slot]))
+            || self.ring_is_invalid(vertex)
+        {
+            self.vertices[vertex] = previous;
+            return None;
+        }
+        self.changed_vertices.push(vertex);
+
+        if mode == TriangulationMode::ConstrainedDelaunay {
+            let constraints = &self.constraints;
+            let flipped = delaunay::flip_edges(
+                &self.vertices,
+                &mut self.triangles,
+                &mut self.adjacency,
+                star.iter()
+                    .flat_map(|slot| delaunay::triangle_edges(&self.committed[*slot]))
+                    .map(delaunay::edge_key)
+                    .collect(),
+                |edge| constraints.contains(&edge),
+            );
+            for slot in flipped {
+                self.committed[slot] = self.triangles[slot];
+                self.changed_triangles.push(slot);
+            }
+        }
+        Some(())
+    }
+
+    /// Takes the triangles back to the committed ones.
+    fn restore(&mut self) {
+        for slot in mem::take(&mut self.extended) {
+            delaunay::remove_triangle(&mut self.adjacency, &self.triangles[slot], slot);
+            self.triangles[slot] = match self.committed.get(slot) {
+                Some(triangle) => {
+                    delaunay::add_triangle(&mut self.adjacency, triangle, slot);
+                    *triangle
+                }
+                None => Self::no_ear(self.closing_edge),
+            };
+            self.changed_triangles.push(slot);
+        }
+    }
+
+    fn take_changes(&mut self) -> MeshUpdate {
+        let mut vertices = mem::take(&mut self.changed_vertices);
+        let mut triangles = mem::take(&mut self.changed_triangles);
+        vertices.sort_unstable();
+        vertices.dedup();
+        triangles.sort_unstable();
+        triangles.dedup();
+        MeshUpdate::Partial {
+            vertices,
+            triangles,
+        }
+    }
+
+    fn no_ear((start, end): (usize, usize)) -> [u32; 3] {
+        [start as u32, start as u32, end as u32]
+    }
+
+    fn is_ccw(&self, triangle: &[u32; 3]) -> bool {
+        let point = |i: u32| self.vertices[i as usize];
+        orient2d(point(triangle[0]), point(triangle[1]), point(triangle[2])) > 0.0
+    }
+
+    /// Triangles overlapping the notch, found by walking from the triangle on
+    /// the closing edge across the edges that cut into it.
+    fn cavity(&self, notch: [Vec2; 3]) -> Option<Vec<usize>> {
+        let (start, end) = self.closing_edge;
+        let closing_edge = delaunay::edge_key((start as u32, end as u32));
+        let mut cavity = self.adjacency.get(&closing_edge)?.clone();
+
+        let mut i = 0;
+        while let Some(slot) = cavity.get(i) {
+            for edge in delaunay::triangle_edges(&self.triangles[*slot]) {
+                let edge = delaunay::edge_key(edge);
+                let (a, b) = (
+                    self.vertices[edge.0 as usize],
+                    self.vertices[edge.1 as usize],
+                );
+                if edge == closing_edge || !Self::cuts(notch, a, b) {
+                    continue;
+                }
+                for neighbor in &self.adjacency[&edge] {
+                    if !cavity.contains(neighbor) {
+                        cavity.push(*neighbor);
+                    }
+                }
+            }
+            i += 1;
+        }
+        Some(cavity)
+    }
+
+    /// Outline of the cavity from the end of the closing edge round to its
+    /// start, as long as it is a single ring that does not touch itself.
+    fn boundary(&self, cavity: &[usize]) -> Option<Vec<u32>> {
+        let edges: Vec<_> = cavity
+            .iter()
+            .flat_map(|slot| delaunay::triangle_edges(&self.triangles[*slot]))
+            .collect();
+        let boundary: Vec<_> = edges
+            .iter()
+            .filter(|(a, b)| !edges.contains(&(*b, *a)))
+            .collect();
+
+        let (start, end) = (self.closing_edge.0 as u32, self.closing_edge.1 as u32);
+        let mut ring = vec![end];
+        while ring.len() < boundary.len() {
+            let mut next = boundary.iter().filter(|(a, _)| Some(a) == ring.last());
+            match (next.next(), next.next()) {
+                (Some((_, b)), None) => ring.push(*b),
+                _ => return None,
+            }
+        }
+        (ring.last() == Some(&start) && !ring[..ring.len() - 1].contains(&start)).then_some(ring)
+    }
+
+    /// Whether the segment from `a` to `b` crosses the inside of the
+    /// counter-clockwise triangle, which is the case unless the line through
+    /// one of them separates the two.
+    fn cuts(triangle: [Vec2; 3], a: Vec2, b: Vec2) -> bool {
+        let beside_edge = (0..3).any(|i| {
+            let (start, end) = (triangle[i], triangle[(i + 1) % 3]);
+            orient2d(start, end, a) <= 0.0 && orient2d(start, end, b) <= 0.0
+        });
+        let sides = triangle.map(|point| orient2d(a, b, point));
+        let beside_segment =
+            sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0);
+        !beside_edge && !beside_segment
+    }
+
+    fn ring_edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rings
+            .iter()
+            .flat_map(|ring| (0..ring.len()).map(|i| (ring[i], ring[(i + 1) % ring.len()])))
+    }
+
+    /// Whether the triangles around a vertex leave no gap next to it. Ears
+    /// along collinear points are left out of the triangles, and moving one
+    /// of their points would open them up.
+    fn is_surrounded(&self, vertex: usize, star: &[usize]) -> bool {
+        let vertex = vertex as u32;
+        let ring_edges_in_mesh = self.ring_neighbors(vertex as usize).iter().all(|neighbor| {
+            let edge = delaunay::edge_key((vertex, *neighbor as u32));
+            self.adjacency.contains_key(&edge)
+        });
+        ring_edges_in_mesh
+            && star
+                .iter()
+                .flat_map(|slot| delaunay::triangle_edges(&self.committed[*slot]))
+                .map(delaunay::edge_key)
+                .filter(|(a, b)| *a == vertex || *b == vertex)
+                .all(|edge| {
+                    self.constraints.contains(&edge)
+                        || self.adjacency.get(&edge).map(Vec::len) == Some(2)
+                })
+    }
+
+    fn ring_neighbors(&self, vertex: usize) -> [usize; 2] {
+        let (ring, position) = self.ring_positions[vertex];
+        let ring = &self.rings[ring];
+        [
+            ring[(position + ring.len() - 1) % ring.len()],
+            ring[(position + 1) % ring.len()],
+        ]
+    }
+
+    /// Whether the two ring edges of a moved vertex touch any other ring edge,
+    /// or its ring got turned around.
+    fn ring_is_invalid(&self, vertex: usize) -> bool {
+        let (ring_index, _) = self.ring_positions[vertex];
+        let ring = &self.rings[ring_index];
+        let neighbors = self.ring_neighbors(vertex);
+        let point = |i: usize| self.vertices[i];
+        let moved = neighbors.map(|neighbor| Segment {
+            start: point(neighbor),
+            end: point(vertex),
+        });
+
+        let doubled_area = (0..ring.len()).fold(0.0, |area, i| {
+            area + point(ring[i]).cross(point(ring[(i + 1) % ring.len()]))
+        });
+        let turned = (doubled_area > 0.0) != (ring_index == 0);
+
+        turned
+            || self
+                .ring_edges()
+                .filter(|(start, end)| *start != vertex && *end != vertex)
+                .any(|(start, end)| {
+                    let edge = Segment {
+                        start: point(start),
+                        end: point(end),
+                    };
+                    Self::touches(&edge, point(vertex))
+                        || moved.iter().zip(neighbors).any(|(moved, neighbor)| {
+                            Intersector::segments_intersect(moved, &edge)
+                                || (start != neighbor && Self::touches(moved, edge.start))
+                        })
+                })
+    }
+
+    fn touches(segment: &Segment<Vec2>, point: Vec2) -> bool {
+        let (start, end) = (segment.start, segment.end);
+        orient2d(start, end, point) == 0.0
+            && point.x >= start.x.min(end.x)
+            && point.x <= start.x.max(end.x)
+            && point.y >= start.y.min(end.y)
+            && point.y <= start.y.max(end.y)
+    }
+
+    fn overlaps(&self, cursor: Vec2) -> bool {
+        let vertices = &self.vertices;
+        let (start, end) = self.closing_edge;
+        let triangle = (vertices[start], cursor, vertices[end]);
+        let rubber_band = [
+            Segment {
+                start: vertices[start],
+                end: cursor,
+            },
+            Segment {
+                start: cursor,
+                end: vertices[end],
+            },
+        ];
+
+        self.ring_edges()
+            .filter(|edge| *edge != (start, end))
+            .any(|(edge_start, edge_end)| {
+                let edge = Segment {
+                    start: vertices[edge_start],
+                    end: vertices[edge_end],
+                };
+
+                Self::touches(&edge, cursor)
+                    || Triangulator::triangle_contains(triangle, edge.start)
+                    || Triangulator::triangle_contains(triangle, edge.end)
+                    || rubber_band
+                        .iter()
+                        .any(|band| Intersector::segments_intersect(band, &edge))
+            })
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{application::MouseEvent, vec::Vec2};
+use crate::{input::InputEvent, matrix::Matrix, vec::Vec2};
 
-use self::{matrix::Matrix, shader_context::ShaderContext};
+use self::shader_context::ShaderContext;
 
 pub mod shader;
 pub mod shader_context;
@@ -8,12 +8,12 @@ pub mod ui_root;
 pub mod vertex;
 
 mod image;
-mod matrix;
 mod mesh;
 mod ui_dropdown;
 mod ui_group;
 mod ui_image_editor;
 mod ui_image_selection;
+mod ui_mode_toggle;
 
 #[derive(Clone, Copy)]
 pub struct BoundingRect {
@@ -23,6 +23,7 @@ pub struct BoundingRect {
     height: f32,
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum EditMode {
     Edit,
     Preview,
@@ -49,7 +50,7 @@ impl BoundingRect {
 }
 
 trait UiElementInner {
-    fn on_mouse_event(&mut self, pos: (f32, f32), event: MouseEvent) -> bool;
+    fn on_event(&mut self, pos: (f32, f32), event: InputEvent) -> bool;
 
     fn get_world_matrix(&self) -> &Matrix;
     fn render(&self, context: &mut ShaderContext);
@@ -63,7 +64,7 @@ trait UiElementInner {
 }
 
 pub trait UiElement {
-    fn handle_mouse_event(&mut self, mouse: (f32, f32), event: MouseEvent) -> bool;
+    fn handle_event(&mut self, pos: (f32, f32), event: InputEvent) -> bool;
 
     fn render(&self, context: &mut ShaderContext);
     fn set_position(&mut self, pos: (f32, f32));
@@ -75,12 +76,12 @@ impl<T> UiElement for T
 where
     T: UiElementInner,
 {
-    fn handle_mouse_event(&mut self, pos: (f32, f32), event: MouseEvent) -> bool {
-        if self.get_bounding_box().contains(pos) {
+    fn handle_event(&mut self, pos: (f32, f32), event: InputEvent) -> bool {
+        if !event.is_positional() || self.get_bounding_box().contains(pos) {
             let event_handled = self
                 .get_children_mut()
-                .any(|child| child.handle_mouse_event(pos, event));
-            event_handled || self.on_mouse_event(pos, event)
+                .any(|child| child.handle_event(pos, event));
+            event_handled || self.on_event(pos, event)
         } else {
             false
         }
@@ -60,6 +60,42 @@ where
         }
     }
 
+    /// Uploads only the given vertices over the ones `load` uploaded, the
+    /// vertex buffer has to have kept its size since.
+    pub fn load_vertices(&self, vertices: &[usize]) {
+        let size = mem::size_of::<T>();
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            for i in vertices {
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    (i * size) as isize,
+                    size as isize,
+                    (&self.v_buffer.vertices[*i] as *const T).cast(),
+                );
+            }
+        }
+    }
+
+    /// Uploads only the given primitives of the index buffer, like
+    /// `load_vertices`.
+    pub fn load_indices(&self, primitives: &[usize]) {
+        let size = mem::size_of::<[u32; N]>();
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+            for i in primitives {
+                gl::BufferSubData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    (i * size) as isize,
+                    size as isize,
+                    (&self.i_buffer.indices[*i] as *const [u32; N]).cast(),
+                );
+            }
+        }
+    }
+
     pub fn render(&self) {
         unsafe {
             gl::BindVertexArray(self.vao);
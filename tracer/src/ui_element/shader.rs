@@ -2,9 +2,7 @@ use glad_gl::gl;
 use std::io::Read;
 use std::{fs, io, mem};
 
-use crate::application::AppError;
-
-use super::matrix::Matrix;
+use crate::{application::AppError, matrix::Matrix};
 
 impl From<io::Error> for AppError {
     fn from(from: io::Error) -> AppError {
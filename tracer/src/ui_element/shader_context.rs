@@ -1,6 +1,6 @@
-use crate::application::AppError;
+use crate::{application::AppError, matrix::Matrix};
 
-use super::{matrix::Matrix, shader::ShaderProgram};
+use super::shader::ShaderProgram;
 
 pub struct ShaderContext {
     pub col_shader: ShaderProgram,
@@ -1,14 +1,27 @@
-use crate::vec::Vec2;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
-use super::{
+use crate::{
+    input::{InputEvent, MouseButton},
     matrix::Matrix,
+    vec::Vec2,
+};
+
+use super::{
     mesh::Mesh,
-    vertex::{IndexBuffer, MeshGenerator, MeshType, VertexPC},
+    shader_context::ShaderContext,
+    vertex::{MeshGenerator, MeshType, VertexPC},
     BoundingRect, UiElement, UiElementInner,
 };
 
+/// Picks one of the shared options, each click moves on to the next one.
+/// The options are shared so that whoever owns them can change them while
+/// the dropdown is shown.
 pub struct Dropdown {
-    options: Vec<&'static str>,
+    options: Rc<RefCell<Vec<String>>>,
+    selected: Rc<Cell<usize>>,
     world_matrix: Matrix,
     bound_rect: BoundingRect,
     children: Vec<Box<dyn UiElement>>,
@@ -18,14 +31,22 @@ pub struct Dropdown {
 }
 
 impl Dropdown {
-    pub fn new(pos: Vec2, size: Vec2, options: Vec<&'static str>) -> Self {
+    /// `pos` is relative to the parent at `origin`, which events are not.
+    pub fn new(
+        origin: Vec2,
+        pos: Vec2,
+        size: Vec2,
+        options: Rc<RefCell<Vec<String>>>,
+        selected: Rc<Cell<usize>>,
+    ) -> Self {
         let (vertices, indices) = VertexPC::quad(size.x, size.y);
         let button = Mesh::build(vertices, indices, MeshType::Triangles);
 
         Dropdown {
             options,
-            world_matrix: Matrix::ident(),
-            bound_rect: BoundingRect::new(pos, size),
+            selected,
+            world_matrix: Matrix::translate(pos.x, pos.y, 0.0),
+            bound_rect: BoundingRect::new(origin + pos, size),
             children: vec![],
             button,
         }
@@ -33,27 +54,45 @@ impl Dropdown {
 }
 
 impl UiElementInner for Dropdown {
-    fn on_mouse_event(&mut self, pos: (f32, f32), event: crate::application::MouseEvent) -> bool {
-        false
+    fn on_event(&mut self, _pos: (f32, f32), event: InputEvent) -> bool {
+        match event {
+            InputEvent::MousePress {
+                button: MouseButton::Left,
+                ..
+            } => {
+                let count = self.options.borrow().len();
+                if count > 0 {
+                    self.selected.set((self.selected.get() + 1) % count);
+                }
+                true
+            }
+            _ => false,
+        }
     }
 
-    fn get_world_matrix(&self) -> &super::matrix::Matrix {
+    fn get_world_matrix(&self) -> &Matrix {
         &self.world_matrix
     }
 
-    fn render(&self, context: &mut super::shader_context::ShaderContext) {
-        self.button.render();
+    fn render(&self, context: &mut ShaderContext) {
+        if context
+            .col_shader
+            .set_matrix("world\x00", context.get_matrix())
+        {
+            self.button.render();
+        }
     }
 
-    fn set_position(&mut self, pos: (f32, f32)) {}
+    fn set_position(&mut self, (x, y): (f32, f32)) {
+        self.bound_rect.left = x;
+        self.bound_rect.top = y;
+    }
 
-    fn get_children<'a>(&'a self) -> Box<dyn Iterator<Item = &dyn super::UiElement> + 'a> {
+    fn get_children<'a>(&'a self) -> Box<dyn Iterator<Item = &dyn UiElement> + 'a> {
         Box::new(self.children.iter().map(|child| &**child as &dyn UiElement))
     }
 
-    fn get_children_mut<'a>(
-        &'a mut self,
-    ) -> Box<dyn Iterator<Item = &mut dyn super::UiElement> + 'a> {
+    fn get_children_mut<'a>(&'a mut self) -> Box<dyn Iterator<Item = &mut dyn UiElement> + 'a> {
         Box::new(
             self.children
                 .iter_mut()
@@ -61,7 +100,7 @@ impl UiElementInner for Dropdown {
         )
     }
 
-    fn get_bounding_box(&self) -> super::BoundingRect {
+    fn get_bounding_box(&self) -> BoundingRect {
         self.bound_rect
     }
 }
@@ -1,7 +1,6 @@
-use crate::{application::MouseEvent, vec::Vec2};
+use crate::{input::InputEvent, matrix::Matrix, vec::Vec2};
 
 use super::{
-    matrix::Matrix,
     mesh::Mesh,
     shader_context::ShaderContext,
     vertex::{Color, MeshGenerator, MeshType, VertexPC},
@@ -34,8 +33,8 @@ impl UiElementInner for UiGroup {
         self.bounding_rect
     }
 
-    fn on_mouse_event(&mut self, _pos: (f32, f32), _event: MouseEvent) -> bool {
-        true
+    fn on_event(&mut self, _pos: (f32, f32), event: InputEvent) -> bool {
+        event.is_positional()
     }
 
     fn get_world_matrix(&self) -> &Matrix {
@@ -1,38 +1,96 @@
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    path::Path,
+    rc::Rc,
+};
+
 use crate::{
-    application::{AppError, MouseEvent},
+    annotation::{Annotation, ImageFrame, Project},
+    application::AppError,
+    formats::{coco, cvat, geojson, labelme, mask, voc},
+    history::{Command, History, Shape, VertexRef},
+    input::{InputEvent, Key, Modifiers, MouseButton},
+    keymap::{Action, Keymap},
+    matrix::Matrix,
     ui_element::vertex::Color,
+    vec::Vec2,
+    view::View,
 };
 
 use super::{
     image::Image,
-    matrix::Matrix,
     mesh::Mesh,
     shader_context::ShaderContext,
-    ui_image_selection::UiImageSelection,
+    ui_image_selection::{PointHandle, TransformHandle, UiImageSelection, HANDLE_RADIUS},
     vertex::{MeshGenerator, MeshType, VertexPT},
     BoundingRect, EditMode, UiElement, UiElementInner,
 };
 
-pub struct UiImageEditor<'a> {
+const IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "bmp", "gif", "tif", "tiff"];
+/// Smallest factor a selection can be scaled down by in one drag.
+const MIN_SCALE: f32 = 0.05;
+
+/// A point being dragged, `from` is where it was in image pixels.
+#[derive(Clone, Copy)]
+struct DraggedPoint {
+    handle: PointHandle,
+    from: Vec2,
+}
+
+/// A whole selection being transformed, positions are in editor space.
+#[derive(Clone, Copy)]
+struct ActiveTransform {
+    handle: TransformHandle,
+    pivot: Vec2,
+    start: Vec2,
+}
+
+pub struct UiImageEditor {
     image: Image,
     quad: Mesh<VertexPT, 3>,
-    children: Vec<Box<UiImageSelection<'a>>>,
-    world_matrix: Matrix,
+    children: Vec<UiImageSelection>,
+    view: View,
     screen_resolution: (f32, f32),
     size: (f32, f32),
     pos: (f32, f32),
-    sensitivity: f32,
-    edit_mode: EditMode,
+    panning: bool,
+    edit_mode: Rc<Cell<EditMode>>,
+    /// Mode the editor last caught up with, the shared one can be flipped by
+    /// the toolbox in between.
+    applied_edit_mode: EditMode,
+    handle_radius: Rc<Cell<f32>>,
+    quit_requested: bool,
     selection: Option<u32>,
+    drawing: bool,
+    dragged_point: Option<DraggedPoint>,
+    active_transform: Option<ActiveTransform>,
+    keymap: Keymap,
+    history: History,
+    project: Project,
+    current_image: Option<usize>,
+    current_label: Rc<Cell<usize>>,
+    /// Label the editor last caught up with, the shared one can be picked by
+    /// the toolbox in between.
+    applied_label: usize,
+    /// Names of the project labels, kept up to date for the toolbox.
+    label_names: Rc<RefCell<Vec<String>>>,
+    frame: ImageFrame,
 }
 
-impl<'a> UiImageEditor<'a> {
+impl UiImageEditor {
     pub fn new(
         (x, y): (f32, f32),
         (width, height): (f32, f32),
         screen_resolution: (f32, f32),
-    ) -> UiImageEditor<'a> {
+        project: Project,
+    ) -> UiImageEditor {
         let (vertices, indices) = VertexPT::quad(width, height);
+        let label_names = project
+            .labels
+            .iter()
+            .map(|label| label.name.clone())
+            .collect();
         let quad = Mesh::build(vertices, indices, MeshType::Triangles);
 
         UiImageEditor {
@@ -42,22 +100,158 @@ impl<'a> UiImageEditor<'a> {
             pos: (x, y),
             size: (width, height),
             children: vec![],
-            world_matrix: Matrix::translate(x, y, 0.0),
-            sensitivity: 0.1,
-            edit_mode: EditMode::Preview,
+            view: View::new((x, y), (width, height)),
+            panning: false,
+            edit_mode: Rc::new(Cell::new(EditMode::Preview)),
+            applied_edit_mode: EditMode::Preview,
+            handle_radius: Rc::new(Cell::new(HANDLE_RADIUS)),
+            quit_requested: false,
             selection: None,
+            drawing: false,
+            dragged_point: None,
+            active_transform: None,
+            keymap: Keymap::default(),
+            history: History::default(),
+            project,
+            current_image: None,
+            current_label: Rc::new(Cell::new(0)),
+            applied_label: 0,
+            label_names: Rc::new(RefCell::new(label_names)),
+            frame: ImageFrame {
+                resolution: (1.0, 1.0),
+                half_extent: (width, height),
+            },
         }
     }
 
-    pub fn load_image(mut self, filename: &str) -> Result<UiImageEditor<'a>, AppError> {
+    pub fn with_keymap(mut self, keymap: Keymap) -> UiImageEditor {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Window size in pixels, which the 1:1 pixel view depends on. Screen
+    /// space spans the window height over two units.
+    pub fn with_window_size(mut self, window_size: (u32, u32)) -> UiImageEditor {
+        self.view = self.view.with_window_size(window_size);
+        self
+    }
+
+    pub fn load_image(mut self, filename: &str) -> Result<UiImageEditor, AppError> {
+        self.open_image(filename)?;
+        Ok(self)
+    }
+
+    fn open_image(&mut self, filename: &str) -> Result<(), AppError> {
         let image = Image::from_file(filename)?;
 
         self.update_resolution((image.width() as f32, image.height() as f32));
+        let index = self
+            .project
+            .add_image(filename, image.width(), image.height());
+        if self.project.images[index].annotations.is_empty() {
+            labelme::load_image(&mut self.project, index)?;
+        }
+        self.current_image = Some(index);
         self.image = image;
+        self.sync_selections();
+        self.view.zoom_to_fit();
+
+        Ok(())
+    }
+
+    pub fn import_coco(mut self, filename: &str) -> Result<UiImageEditor, AppError> {
+        let dataset = coco::load(filename)?;
+        self.history
+            .import(&mut self.project, |project| coco::import(project, &dataset));
+        self.sync_selections();
+
+        Ok(self)
+    }
+
+    pub fn import_cvat(mut self, filename: &str) -> Result<UiImageEditor, AppError> {
+        let annotations = cvat::load(filename)?;
+        let directory = Path::new(filename).parent().unwrap_or(Path::new("."));
+        // Opened outside the history so undoing the import keeps what the
+        // LabelMe files of the new images held.
+        cvat::open_images(&mut self.project, &annotations, directory)?;
+        let result = self.history.import(&mut self.project, |project| {
+            cvat::import(project, &annotations, directory)
+        })?;
+        if result.points > 0 {
+            eprintln!(
+                "Skipped {} CVAT <points> of {}, keypoints have no area to annotate",
+                result.points, filename
+            );
+        }
+        if result.skipped > 0 {
+            eprintln!(
+                "Skipped {} CVAT shapes of {} that are not polygons or boxes",
+                result.skipped, filename
+            );
+        }
+        self.sync_selections();
+
+        Ok(self)
+    }
+
+    pub fn import_geojson(mut self, directory: &str) -> Result<UiImageEditor, AppError> {
+        let result = self.history.import(&mut self.project, |project| {
+            geojson::import_directory(project, directory)
+        })?;
+        if result.skipped > 0 {
+            eprintln!(
+                "Skipped {} GeoJSON features of {} that are not polygons or multipolygons",
+                result.skipped, directory
+            );
+        }
+        self.sync_selections();
+
+        Ok(self)
+    }
+
+    pub fn import_masks(
+        mut self,
+        directory: &str,
+        tolerance: f32,
+    ) -> Result<UiImageEditor, AppError> {
+        self.history.import(&mut self.project, |project| {
+            mask::import_directory(project, directory, tolerance)
+        })?;
+        self.sync_selections();
+
+        Ok(self)
+    }
+
+    pub fn import_voc(mut self, directory: &str) -> Result<UiImageEditor, AppError> {
+        self.history.import(&mut self.project, |project| {
+            voc::import_directory(project, directory)
+        })?;
+        self.sync_selections();
 
         Ok(self)
     }
 
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit_requested
+    }
+
+    pub fn edit_mode(&self) -> Rc<Cell<EditMode>> {
+        self.edit_mode.clone()
+    }
+
+    /// Label new annotations get, shared with the toolbox label picker.
+    pub fn current_label(&self) -> Rc<Cell<usize>> {
+        self.current_label.clone()
+    }
+
+    pub fn label_names(&self) -> Rc<RefCell<Vec<String>>> {
+        self.label_names.clone()
+    }
+
     fn update_resolution(&mut self, img_resolution: (f32, f32)) {
         let axis_scaling = (
             self.screen_resolution.0 as f32 / img_resolution.0,
@@ -68,75 +262,707 @@ impl<'a> UiImageEditor<'a> {
 
         println!("resolution:  {} {} ", img_resolution.0, img_resolution.1);
 
-        let (vertices, _) = VertexPT::quad(
+        let half_extent = (
             self.size.0 * img_resolution.0 * maximal_scaling,
             self.size.1 * img_resolution.1 * maximal_scaling,
         );
+        let (vertices, _) = VertexPT::quad(half_extent.0, half_extent.1);
         self.quad.v_buffer = vertices;
+        self.frame = ImageFrame {
+            resolution: img_resolution,
+            half_extent,
+        };
 
         self.quad.load();
     }
 
-    fn scale_image(&mut self, (x, y): (f32, f32), factor: f32) {
-        let scale = self.world_matrix.data[0][0];
-        let factor = 1.0 + factor * self.sensitivity;
+    fn add_point(&mut self, pos: (f32, f32)) {
+        let pos = self.view.to_editor(pos);
+        if !self.drawing {
+            return;
+        }
+        let (Some(image), Some(selection)) = (self.current_image, self.selection) else {
+            return;
+        };
+        let Some(selection) = self.children.get_mut(selection as usize) else {
+            return;
+        };
+        let Some(annotation) = self
+            .project
+            .image(image)
+            .and_then(|image| image.annotation(selection.annotation_id()))
+        else {
+            return;
+        };
 
-        if (scale > 0.2 || factor > 1.0) && (scale < 50.0 || factor < 1.0) {
-            let mat = Matrix::translate(-x, -y, 0.0)
-                * Matrix::scale(factor)
-                * Matrix::translate(x, y, 0.0);
-            self.world_matrix = self.world_matrix * mat;
+        let command = Command::AddPoint {
+            vertex: VertexRef {
+                image,
+                annotation: annotation.id,
+                hole: None,
+                index: annotation.polygon.len(),
+            },
+            point: self.frame.to_pixels(Vec2::new(pos)),
+        };
+        if self.history.execute(&mut self.project, command).is_some() {
+            selection.add_point(pos);
         }
     }
 
-    fn add_point(&mut self, pos: (f32, f32)) {
-        let pos = self.transform_cursor_pos(pos);
-        if let Some(latest_selection) = self.children.last_mut() {
-            latest_selection.add_point(pos);
+    /// Grabs the point under the cursor, or inserts one when clicking the
+    /// midpoint of an edge of the selected selection.
+    fn grab_point(&mut self, pos: (f32, f32)) {
+        if self.edit_mode.get() == EditMode::Preview {
+            return;
+        }
+        let pos = self.view.to_editor(pos);
+        let radius = self.handle_radius();
+
+        let selected = self
+            .selection
+            .and_then(|selection| self.children.get(selection as usize));
+        if let Some(handle) = selected.and_then(|selection| selection.hit_point(pos, radius)) {
+            self.start_drag(handle);
+            return;
+        }
+        if let Some((handle, pivot)) = selected.and_then(|selection| {
+            Some((
+                selection.hit_transform_handle(pos, radius)?,
+                selection.centroid()?,
+            ))
+        }) {
+            self.active_transform = Some(ActiveTransform {
+                handle,
+                pivot,
+                start: Vec2::new(pos),
+            });
+            return;
+        }
+
+        let hit = self
+            .children
+            .iter()
+            .enumerate()
+            .find_map(|(index, child)| Some((index as u32, child.hit_point(pos, radius)?)));
+        if let Some((selection, handle)) = hit {
+            self.select(Some(selection));
+            self.start_drag(handle);
+            return;
+        }
+
+        let Some(image) = self.current_image else {
+            return;
+        };
+        let Some(selection) = self
+            .selection
+            .and_then(|selection| self.children.get_mut(selection as usize))
+        else {
+            return;
+        };
+        selection.select_point(None);
+        let Some((handle, midpoint)) = selection.hit_midpoint(pos, radius) else {
+            return;
+        };
+
+        let command = Command::AddPoint {
+            vertex: VertexRef {
+                image,
+                annotation: selection.annotation_id(),
+                hole: handle.hole,
+                index: handle.index,
+            },
+            point: self.frame.to_pixels(midpoint),
+        };
+        if self.history.execute(&mut self.project, command).is_none() {
+            return;
+        }
+        if selection
+            .insert_point(handle, (midpoint.x, midpoint.y))
+            .is_some()
+        {
+            self.start_drag(handle);
+        } else {
+            self.sync_selections();
+        }
+    }
+
+    fn start_drag(&mut self, handle: PointHandle) {
+        let Some(selection) = self
+            .selection
+            .and_then(|selection| self.children.get_mut(selection as usize))
+        else {
+            return;
+        };
+        selection.select_point(Some(handle));
+        self.dragged_point = selection.point(handle).map(|point| DraggedPoint {
+            handle,
+            from: self.frame.to_pixels(point),
+        });
+    }
+
+    fn drag_point(&mut self, pos: (f32, f32)) {
+        let pos = self.view.to_editor(pos);
+        let (Some(dragged), Some(selection)) = (
+            self.dragged_point,
+            self.selection
+                .and_then(|selection| self.children.get_mut(selection as usize)),
+        ) else {
+            return;
+        };
+        selection.move_point(dragged.handle, pos);
+    }
+
+    /// Previews the transform of the selection for the cursor at `pos`.
+    fn transform_selection(&mut self, pos: (f32, f32)) {
+        let pos = Vec2::new(self.view.to_editor(pos));
+        let (Some(active), Some(selection)) = (
+            self.active_transform,
+            self.selection
+                .and_then(|selection| self.children.get_mut(selection as usize)),
+        ) else {
+            return;
+        };
+        let (pivot, start) = (active.pivot, active.start);
+        let about_pivot = |matrix| {
+            Matrix::translate(-pivot.x, -pivot.y, 0.0)
+                * matrix
+                * Matrix::translate(pivot.x, pivot.y, 0.0)
+        };
+
+        match active.handle {
+            TransformHandle::Move => UiElementInner::set_position(
+                selection,
+                (pivot.x + pos.x - start.x, pivot.y + pos.y - start.y),
+            ),
+            TransformHandle::Rotate => {
+                let (from, to) = (start - pivot, pos - pivot);
+                let angle = to.y.atan2(to.x) - from.y.atan2(from.x);
+                selection.set_transform(about_pivot(Matrix::rotate(angle)));
+            }
+            TransformHandle::Scale => {
+                let (from, to) = (start - pivot, pos - pivot);
+                if from * from > f32::EPSILON {
+                    let factor = ((to * to) / (from * from)).sqrt().max(MIN_SCALE);
+                    selection.set_transform(about_pivot(Matrix::scale(factor)));
+                }
+            }
+        }
+    }
+
+    /// Bakes the transform of the selection into its points once the handle
+    /// is released.
+    fn finish_transform(&mut self) {
+        if self.active_transform.take().is_none() {
+            return;
+        }
+        let (Some(image), Some(selection)) = (
+            self.current_image,
+            self.selection
+                .and_then(|selection| self.children.get_mut(selection as usize)),
+        ) else {
+            return;
+        };
+        let Some(from) = self
+            .project
+            .image(image)
+            .and_then(|image| image.annotation(selection.annotation_id()))
+            .map(Shape::of)
+        else {
+            return;
+        };
+
+        let (outline, holes) = selection.apply_transform();
+        let to_pixels = |ring: Vec<Vec2>| {
+            ring.into_iter()
+                .map(|point| self.frame.to_pixels(point))
+                .collect::<Vec<_>>()
+        };
+        let to = Shape {
+            polygon: to_pixels(outline),
+            holes: holes.into_iter().map(to_pixels).collect(),
+        };
+        if to != from {
+            let command = Command::Reshape {
+                image,
+                annotation: selection.annotation_id(),
+                from,
+                to,
+            };
+            self.history.execute(&mut self.project, command);
+        }
+    }
+
+    /// Records the move of the dragged point once it is released.
+    fn release_point(&mut self) {
+        let Some(dragged) = self.dragged_point.take() else {
+            return;
+        };
+        let (Some(image), Some(selection)) = (
+            self.current_image,
+            self.selection
+                .and_then(|selection| self.children.get(selection as usize)),
+        ) else {
+            return;
+        };
+        let Some(to) = selection.point(dragged.handle) else {
+            return;
+        };
+
+        let to = self.frame.to_pixels(to);
+        if to != dragged.from {
+            let command = Command::MovePoint {
+                vertex: VertexRef {
+                    image,
+                    annotation: selection.annotation_id(),
+                    hole: dragged.handle.hole,
+                    index: dragged.handle.index,
+                },
+                from: dragged.from,
+                to,
+            };
+            self.history.execute(&mut self.project, command);
+        }
+    }
+
+    fn delete_point(&mut self) {
+        if self.edit_mode.get() == EditMode::Preview {
+            return;
+        }
+        let (Some(image), Some(selection)) = (
+            self.current_image,
+            self.selection
+                .and_then(|selection| self.children.get_mut(selection as usize)),
+        ) else {
+            return;
+        };
+        let Some(handle) = selection.selected_point() else {
+            return;
+        };
+
+        let vertex = VertexRef {
+            image,
+            annotation: selection.annotation_id(),
+            hole: handle.hole,
+            index: handle.index,
+        };
+        let Some(command) = Command::delete_point(&self.project, vertex) else {
+            return;
+        };
+        if self.history.execute(&mut self.project, command).is_none() {
+            return;
+        }
+        if selection.remove_point(handle).is_none() {
+            self.sync_selections();
+        }
+    }
+
+    fn select(&mut self, selection: Option<u32>) {
+        if let Some(previous) = self
+            .selection
+            .and_then(|previous| self.children.get_mut(previous as usize))
+        {
+            previous.set_selected(false);
+        }
+        self.selection = selection;
+        if let Some(selection) = selection.and_then(|index| self.children.get_mut(index as usize)) {
+            selection.set_selected(true);
         }
     }
 
     fn update_cursor(&mut self, pos: (f32, f32)) {
-        let pos = self.transform_cursor_pos(pos);
-        if let Some(latest_selection) = self.children.last_mut() {
-            latest_selection.update_cursor(pos);
+        let pos = self.view.to_editor(pos);
+        if !self.drawing {
+            return;
+        }
+        if let Some(selection) = self.selection {
+            if let Some(selection) = self.children.get_mut(selection as usize) {
+                selection.update_cursor(pos);
+            }
+        }
+    }
+
+    /// Radius of the point and transform handles in editor units, which
+    /// keeps them the same size on screen at any zoom.
+    fn handle_radius(&self) -> f32 {
+        HANDLE_RADIUS / self.view.scale().0
+    }
+
+    fn add_selection(&mut self) {
+        let Some(image) = self.current_image else {
+            return;
+        };
+        self.close_polygon();
+
+        let annotation = self.project.new_annotation(self.applied_label, vec![]);
+        let id = annotation.id;
+        let command = Command::CreateAnnotation { image, annotation };
+        if self.history.execute(&mut self.project, command).is_some() {
+            self.select(None);
+            self.set_edit_mode(EditMode::Edit);
+            let color = self.label_color(self.applied_label);
+            self.children.push(UiImageSelection::new(
+                id,
+                color,
+                self.edit_mode.clone(),
+                self.handle_radius.clone(),
+            ));
+            self.selection = Some((self.children.len() - 1) as u32);
+            self.drawing = true;
+        }
+    }
+
+    /// Finishes the polygon being drawn, dropping it if it is too small to
+    /// enclose anything.
+    fn close_polygon(&mut self) {
+        if !self.drawing {
+            return;
+        }
+        self.drawing = false;
+
+        let points = self
+            .selected_annotation()
+            .map_or(0, |annotation| annotation.polygon.len());
+        if points < 3 {
+            self.delete_selection();
+        } else if let Some(selection) = self
+            .selection
+            .and_then(|selection| self.children.get_mut(selection as usize))
+        {
+            selection.close();
+            self.select(self.selection);
+        }
+    }
+
+    /// Drops the polygon being drawn, quitting when there is none so that
+    /// Escape keeps closing the window.
+    fn cancel_polygon(&mut self) {
+        match self.drawing {
+            true => self.delete_selection(),
+            false => self.quit_requested = true,
+        }
+    }
+
+    fn delete_selection(&mut self) {
+        let (Some(image), Some(selection)) = (self.current_image, self.selection.take()) else {
+            return;
+        };
+        self.drawing = false;
+        self.dragged_point = None;
+        self.active_transform = None;
+        if (selection as usize) < self.children.len() {
+            let selection = self.children.remove(selection as usize);
+            if let Some(command) =
+                Command::delete_annotation(&self.project, image, selection.annotation_id())
+            {
+                self.history.execute(&mut self.project, command);
+            }
+        }
+    }
+
+    /// Moves on to the next label, relabelling the selected annotation too.
+    fn cycle_label(&mut self) {
+        if self.project.labels.is_empty() {
+            return;
+        }
+        self.current_label
+            .set((self.applied_label + 1) % self.project.labels.len());
+        self.sync_label();
+    }
+
+    /// Catches up with the shared label, which the shortcut and the toolbox
+    /// picker both change, relabelling the selected annotation.
+    pub fn sync_label(&mut self) {
+        let label = self.current_label.get();
+        if label == self.applied_label {
+            return;
+        }
+        self.applied_label = label;
+
+        let (Some(image), Some(annotation)) = (self.current_image, self.selected_annotation())
+        else {
+            return;
+        };
+        let command = Command::ChangeLabel {
+            image,
+            annotation: annotation.id,
+            from: annotation.label,
+            to: label,
+        };
+        if self.history.execute(&mut self.project, command).is_some() {
+            let color = self.label_color(label);
+            if let Some(selection) = self
+                .selection
+                .and_then(|selection| self.children.get_mut(selection as usize))
+            {
+                selection.set_color(color);
+            }
+        }
+    }
+
+    /// Fill colour of the regions labelled with `label`.
+    fn label_color(&self, label: usize) -> Color {
+        let [r, g, b] = self
+            .project
+            .labels
+            .get(label)
+            .map_or([255, 255, 255], |label| label.color);
+        Color(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 0.5)
+    }
+
+    /// Switches between Preview, which only shows the labelled regions, and
+    /// Edit, which shows the point handles and lets them be moved.
+    fn toggle_edit_mode(&mut self) {
+        self.set_edit_mode(match self.edit_mode.get() {
+            EditMode::Edit => EditMode::Preview,
+            EditMode::Preview => EditMode::Edit,
+        });
+    }
+
+    fn set_edit_mode(&mut self, mode: EditMode) {
+        self.edit_mode.set(mode);
+        self.sync_edit_mode();
+    }
+
+    /// Catches up with the shared edit mode, which the shortcut and the
+    /// toolbox toggle both flip. Leaving Edit finishes the polygon being
+    /// drawn and any drag or transform in progress.
+    pub fn sync_edit_mode(&mut self) {
+        let mode = self.edit_mode.get();
+        if mode == self.applied_edit_mode {
+            return;
+        }
+        self.applied_edit_mode = mode;
+
+        if mode == EditMode::Preview {
+            self.close_polygon();
+            self.finish_transform();
+            self.release_point();
+            if let Some(selection) = self
+                .selection
+                .and_then(|selection| self.children.get_mut(selection as usize))
+            {
+                selection.select_point(None);
+            }
+        }
+    }
+
+    /// Reverts or reapplies the last command and rebuilds the selections from
+    /// the annotations, keeping the selected one if it still exists.
+    fn step_history(&mut self, redo: bool) {
+        let stepped = match redo {
+            true => self.history.redo(&mut self.project).is_some(),
+            false => self.history.undo(&mut self.project).is_some(),
+        };
+        if stepped {
+            self.sync_selections();
         }
     }
 
-    fn transform_cursor_pos(&self, pos: (f32, f32)) -> (f32, f32) {
-        let (scale_pos, scale) = self.matrix_to_scale();
-        let pos = (pos.0 - scale_pos.0, pos.1 - scale_pos.1);
-        (pos.0 / scale, pos.1 / scale)
+    fn selected_annotation(&self) -> Option<&Annotation> {
+        let selection = self.children.get(self.selection? as usize)?;
+        self.project
+            .image(self.current_image?)?
+            .annotation(selection.annotation_id())
+    }
+
+    /// Opens the image `offset` places away from the current one among the
+    /// images of its directory, sorted by name and wrapping around.
+    fn step_image(&mut self, offset: isize) {
+        let Some(path) = self
+            .current_image
+            .and_then(|image| self.project.image(image))
+            .map(|image| image.path.clone())
+        else {
+            return;
+        };
+        let directory = Path::new(&path).parent().unwrap_or(Path::new("."));
+        let Ok(entries) = fs::read_dir(directory) else {
+            return;
+        };
+
+        let mut images: Vec<String> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| {
+                        IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                    })
+            })
+            .filter_map(|path| Some(String::from(path.to_str()?)))
+            .collect();
+        images.sort();
+
+        let Some(current) = images
+            .iter()
+            .position(|image| Path::new(image) == Path::new(&path))
+        else {
+            return;
+        };
+        let next = (current as isize + offset).rem_euclid(images.len() as isize) as usize;
+        if next != current {
+            if let Err(error) = self.open_image(&images[next]) {
+                eprintln!("Failed to open {}: {}", images[next], error.error_msg);
+            }
+        }
     }
 
-    fn matrix_to_scale(&self) -> ((f32, f32), f32) {
-        let matrix = &self.world_matrix.data;
+    fn handle_key(&mut self, key: Key, modifiers: Modifiers) -> bool {
+        let Some(action) = self.keymap.action(key, modifiers) else {
+            return false;
+        };
+        match action {
+            Action::ClosePolygon => self.close_polygon(),
+            Action::CancelPolygon => self.cancel_polygon(),
+            Action::DeleteSelection => self.delete_selection(),
+            Action::DeletePoint => self.delete_point(),
+            Action::NewSelection => self.add_selection(),
+            Action::CycleLabel => self.cycle_label(),
+            Action::NextImage => self.step_image(1),
+            Action::PreviousImage => self.step_image(-1),
+            Action::ZoomToFit => self.view.zoom_to_fit(),
+            Action::ActualSize => self.view.actual_size(&self.frame),
+            Action::ToggleEditMode => self.toggle_edit_mode(),
+            Action::Undo => self.step_history(false),
+            Action::Redo => self.step_history(true),
+            Action::Quit => self.quit_requested = true,
+        }
+        true
+    }
 
-        let scale = matrix[0][0];
-        let vec = (matrix[3][0], matrix[3][1]);
-        ((vec), scale)
+    /// Refreshes the label names shown by the toolbox after imports or undo
+    /// added or removed labels, falling back to the first label when the
+    /// current one is gone.
+    fn sync_label_names(&mut self) {
+        let names: Vec<String> = self
+            .project
+            .labels
+            .iter()
+            .map(|label| label.name.clone())
+            .collect();
+        if self.applied_label >= names.len() {
+            self.current_label.set(0);
+            self.applied_label = 0;
+        }
+        if *self.label_names.borrow() != names {
+            *self.label_names.borrow_mut() = names;
+        }
     }
 
-    fn add_selection<'b: 'a>(&'b mut self) {
-        self.children
-            .push(Box::new(UiImageSelection::<'b>::new(&self.edit_mode)));
-        self.selection = Some((self.children.len() - 1) as u32);
+    fn sync_selections(&mut self) {
+        self.sync_label_names();
+        let selected = self
+            .selection
+            .and_then(|selection| self.children.get(selection as usize))
+            .map(|selection| selection.annotation_id());
+        let annotations = self
+            .current_image
+            .and_then(|image| self.project.image(image))
+            .map(|image| image.annotations.as_slice())
+            .unwrap_or_default();
+
+        self.children = annotations
+            .iter()
+            .map(|annotation| {
+                UiImageSelection::from_annotation(
+                    annotation,
+                    &self.frame,
+                    self.label_color(annotation.label),
+                    self.edit_mode.clone(),
+                    self.handle_radius.clone(),
+                )
+            })
+            .collect();
+        let selection = selected.and_then(|id| {
+            annotations
+                .iter()
+                .position(|annotation| annotation.id == id)
+                .map(|index| index as u32)
+        });
+        self.selection = None;
+        self.dragged_point = None;
+        self.active_transform = None;
+        self.drawing &= selection.is_some();
+        if self.drawing {
+            self.selection = selection;
+        } else {
+            self.select(selection);
+        }
     }
 }
 
-impl<'a> UiElementInner for UiImageEditor<'a> {
-    fn on_mouse_event(&mut self, pos: (f32, f32), event: MouseEvent) -> bool {
+impl UiElementInner for UiImageEditor {
+    fn on_event(&mut self, pos: (f32, f32), event: InputEvent) -> bool {
         match event {
-            MouseEvent::Scroll(s) => self.scale_image(pos, s as f32),
-            MouseEvent::LeftClick => self.add_point(pos),
-            MouseEvent::Movement => self.update_cursor(pos),
-            MouseEvent::RightClick => self.add_selection(),
-            _ => (),
+            InputEvent::KeyPress {
+                key: Key::Char(' '),
+                modifiers,
+            } => {
+                // Space pans while held, it can still be bound to an action.
+                self.panning = true;
+                self.handle_key(Key::Char(' '), modifiers);
+            }
+            InputEvent::KeyRelease {
+                key: Key::Char(' '),
+                ..
+            } => self.panning = false,
+            InputEvent::Drag {
+                button: MouseButton::Middle,
+                delta,
+            } => self.view.pan(&self.frame, delta),
+            InputEvent::Drag {
+                button: MouseButton::Left,
+                delta,
+            } if self.panning => self.view.pan(&self.frame, delta),
+            InputEvent::MousePress {
+                button: MouseButton::Left | MouseButton::Middle,
+                ..
+            } if self.panning => {}
+            InputEvent::MousePress {
+                button: MouseButton::Middle,
+                ..
+            } => {}
+            InputEvent::Scroll(s) => self.view.zoom(&self.frame, pos, s),
+            InputEvent::MousePress {
+                button: MouseButton::Left,
+                ..
+            } if self.drawing => self.add_point(pos),
+            InputEvent::MousePress {
+                button: MouseButton::Left,
+                ..
+            } => self.grab_point(pos),
+            InputEvent::Drag {
+                button: MouseButton::Left,
+                ..
+            } if self.active_transform.is_some() => self.transform_selection(pos),
+            InputEvent::Drag {
+                button: MouseButton::Left,
+                ..
+            } => self.drag_point(pos),
+            InputEvent::MouseRelease {
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.release_point();
+                self.finish_transform();
+            }
+            InputEvent::MouseMove => self.update_cursor(pos),
+            InputEvent::MousePress {
+                button: MouseButton::Right,
+                ..
+            } => self.add_selection(),
+            InputEvent::KeyPress { key, modifiers } | InputEvent::KeyRepeat { key, modifiers } => {
+                return self.handle_key(key, modifiers)
+            }
+            _ => return false,
         }
         true
     }
 
     fn render(&self, context: &mut ShaderContext) {
+        // The selections render after the editor and size their handles
+        // like the hit tests do.
+        self.handle_radius.set(self.handle_radius());
         if context
             .tex_shader
             .set_matrix("world\x00", context.get_matrix())
@@ -148,7 +974,7 @@ impl<'a> UiElementInner for UiImageEditor<'a> {
 
     fn set_position(&mut self, pos: (f32, f32)) {
         self.pos = pos;
-        self.world_matrix = Matrix::translate(pos.0, pos.1, 0.0) * self.world_matrix;
+        self.view.set_position(pos);
     }
 
     fn get_bounding_box(&self) -> super::BoundingRect {
@@ -164,18 +990,18 @@ impl<'a> UiElementInner for UiImageEditor<'a> {
     }
 
     fn get_world_matrix(&self) -> &Matrix {
-        &self.world_matrix
+        self.view.matrix()
     }
 
     fn get_children<'b>(&'b self) -> Box<dyn Iterator<Item = &dyn UiElement> + 'b> {
-        Box::new(self.children.iter().map(|child| &**child as &dyn UiElement))
+        Box::new(self.children.iter().map(|child| child as &dyn UiElement))
     }
 
     fn get_children_mut<'b>(&'b mut self) -> Box<dyn Iterator<Item = &mut dyn UiElement> + 'b> {
         Box::new(
             self.children
                 .iter_mut()
-                .map(|child| &mut **child as &mut dyn UiElement),
+                .map(|child| child as &mut dyn UiElement),
         )
     }
 }
@@ -1,42 +1,123 @@
-use crate::{application::MouseEvent, triangulator::Triangulator, vec::Vec2};
+use std::{cell::Cell, rc::Rc};
 
-use super::{
+use crate::{
+    annotation::{Annotation, AnnotationId, ImageFrame},
+    input::InputEvent,
     matrix::Matrix,
+    triangulator::{MeshUpdate, Triangulator},
+    vec::Vec2,
+};
+
+use super::{
     mesh::Mesh,
     shader_context::ShaderContext,
     vertex::{Color, IndexBuffer, MeshGenerator, MeshType, Position, VertexBuffer, VertexPC},
     BoundingRect, EditMode, UiElement, UiElementInner,
 };
 
-pub struct UiImageSelection<'a> {
+/// A point of the selection, on its outline or on one of its holes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointHandle {
+    pub hole: Option<usize>,
+    pub index: usize,
+}
+
+/// On-canvas handles transforming the whole selection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformHandle {
+    Move,
+    Rotate,
+    Scale,
+}
+
+/// Radius in screen space of the point and transform handles.
+pub const HANDLE_RADIUS: f32 = 0.02;
+
+pub struct UiImageSelection {
+    annotation_id: AnnotationId,
     selection_area: Mesh<VertexPC, 3>,
     selection_point: Mesh<VertexPC, 3>,
+    highlight_point: Mesh<VertexPC, 3>,
+    midpoint: Mesh<VertexPC, 3>,
+    move_handle: Mesh<VertexPC, 3>,
+    rotate_handle: Mesh<VertexPC, 3>,
+    scale_handle: Mesh<VertexPC, 3>,
 
     children: Vec<Box<dyn UiElement>>,
     world_matrix: Matrix,
+    transform: Matrix,
     triangulator: Triangulator,
-    edit_mode: &'a EditMode,
-    selected_point: Option<u32>,
+    edit_mode: Rc<Cell<EditMode>>,
+    /// Radius of the transform handles in editor units, kept up to date by
+    /// the editor as it zooms.
+    handle_radius: Rc<Cell<f32>>,
+    color: Color,
+    selected: bool,
+    selected_point: Option<PointHandle>,
 }
 
-impl<'a> UiImageSelection<'a> {
-    pub fn new(edit_mode: &'a EditMode) -> Self {
+impl UiImageSelection {
+    pub fn new(
+        annotation_id: AnnotationId,
+        color: Color,
+        edit_mode: Rc<Cell<EditMode>>,
+        handle_radius: Rc<Cell<f32>>,
+    ) -> Self {
         let (vertices, indices) = (VertexBuffer::new(vec![]), IndexBuffer::new(vec![]));
         let selection_area = Mesh::build(vertices, indices, MeshType::Triangles);
         let (vertices, indices) = VertexPC::ring(0.01, 0.015, 20);
         let selection_point = Mesh::build(vertices, indices, MeshType::Triangles);
+        let highlight_point = Self::handle_mesh(0.018, 0.024, 20, Color(1.0, 0.8, 0.2, 1.0));
+        let midpoint = Self::handle_mesh(0.004, 0.008, 12, Color(1.0, 1.0, 1.0, 1.0));
+        let move_handle = Self::handle_mesh(0.0, 0.016, 20, Color(1.0, 1.0, 1.0, 0.8));
+        let rotate_handle = Self::handle_mesh(0.01, 0.016, 20, Color(0.3, 0.9, 0.4, 1.0));
+        let scale_handle = Self::handle_mesh(0.008, 0.02, 4, Color(0.3, 0.8, 0.9, 1.0));
 
         UiImageSelection {
+            annotation_id,
             selection_area,
             selection_point,
+            highlight_point,
+            midpoint,
+            move_handle,
+            rotate_handle,
+            scale_handle,
             children: vec![],
             world_matrix: Matrix::translate(0.0, 0.0, -0.1),
+            transform: Matrix::ident(),
             triangulator: Triangulator::new(),
             edit_mode,
+            handle_radius,
+            color,
+            selected: false,
             selected_point: None,
         }
     }
 
+    pub fn from_annotation(
+        annotation: &Annotation,
+        frame: &ImageFrame,
+        color: Color,
+        edit_mode: Rc<Cell<EditMode>>,
+        handle_radius: Rc<Cell<f32>>,
+    ) -> Self {
+        let mut selection = Self::new(annotation.id, color, edit_mode, handle_radius);
+        annotation.polygon.iter().for_each(|point| {
+            selection.triangulator.add(frame.to_editor(*point));
+        });
+        annotation.holes.iter().for_each(|hole| {
+            selection
+                .triangulator
+                .add_hole(hole.iter().map(|point| frame.to_editor(*point)).collect());
+        });
+        selection.update_mesh();
+        selection
+    }
+
+    pub fn annotation_id(&self) -> AnnotationId {
+        self.annotation_id
+    }
+
     pub fn update_cursor(&mut self, (x, y): (f32, f32)) {
         self.triangulator.update(Vec2::new((x, y)));
         self.update_mesh();
@@ -47,26 +128,272 @@ impl<'a> UiImageSelection<'a> {
         self.update_mesh();
     }
 
+    /// Stops the outline from following the cursor by pinning the cursor
+    /// point onto the last committed point.
+    pub fn close(&mut self) {
+        let points = self.triangulator.get_points();
+        if let Some(last) = points.len().checked_sub(2).map(|last| points[last]) {
+            self.triangulator.update(last);
+            self.update_mesh();
+        }
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+        self.selection_area
+            .v_buffer
+            .vertices
+            .iter_mut()
+            .for_each(|vertex| vertex.col = color);
+        self.selection_area.load();
+    }
+
+    /// Marks the selection as the one being edited, which shows the
+    /// midpoints of its edges.
+    pub fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+        if !selected {
+            self.selected_point = None;
+        }
+    }
+
+    pub fn selected_point(&self) -> Option<PointHandle> {
+        self.selected_point
+    }
+
+    pub fn select_point(&mut self, point: Option<PointHandle>) {
+        self.selected_point = point;
+    }
+
+    pub fn point(&self, handle: PointHandle) -> Option<Vec2> {
+        match handle.hole {
+            Some(hole) => self.triangulator.get_holes().get(hole)?.get(handle.index),
+            None => self.triangulator.outline().get(handle.index),
+        }
+        .copied()
+    }
+
+    /// Finds the point closest to `pos` within `radius`.
+    pub fn hit_point(&self, pos: (f32, f32), radius: f32) -> Option<PointHandle> {
+        let pos = Vec2::new(pos);
+        self.rings()
+            .flat_map(|(hole, ring)| {
+                ring.iter()
+                    .enumerate()
+                    .map(move |(index, point)| (PointHandle { hole, index }, *point))
+            })
+            .map(|(handle, point)| (handle, (point - pos) * (point - pos)))
+            .filter(|(_, distance)| *distance <= radius * radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(handle, _)| handle)
+    }
+
+    /// Finds the edge whose midpoint is closest to `pos` within `radius`,
+    /// returning where a point inserted on that edge would go.
+    pub fn hit_midpoint(&self, pos: (f32, f32), radius: f32) -> Option<(PointHandle, Vec2)> {
+        let pos = Vec2::new(pos);
+        self.midpoints()
+            .map(|(handle, midpoint)| {
+                let offset = midpoint - pos;
+                (handle, midpoint, offset * offset)
+            })
+            .filter(|(_, _, distance)| *distance <= radius * radius)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+            .map(|(handle, midpoint, _)| (handle, midpoint))
+    }
+
+    pub fn move_point(&mut self, handle: PointHandle, (x, y): (f32, f32)) -> Option<()> {
+        self.triangulator
+            .move_point(handle.hole, handle.index, Vec2::new((x, y)))?;
+        self.update_mesh();
+        Some(())
+    }
+
+    pub fn insert_point(&mut self, handle: PointHandle, (x, y): (f32, f32)) -> Option<()> {
+        self.triangulator
+            .insert_point(handle.hole, handle.index, Vec2::new((x, y)))?;
+        self.update_mesh();
+        Some(())
+    }
+
+    pub fn remove_point(&mut self, handle: PointHandle) -> Option<()> {
+        self.triangulator.remove_point(handle.hole, handle.index)?;
+        self.selected_point = None;
+        self.update_mesh();
+        Some(())
+    }
+
+    /// Area centroid of the outline, or the mean of its points when it
+    /// encloses no area.
+    pub fn centroid(&self) -> Option<Vec2> {
+        let outline = self.triangulator.outline();
+        let first = *outline.first()?;
+
+        let (area, x, y) = (0..outline.len()).fold((0.0, 0.0, 0.0), |(area, x, y), i| {
+            let (a, b) = (outline[i] - first, outline[(i + 1) % outline.len()] - first);
+            let cross = a.cross(b);
+            (
+                area + cross,
+                x + (a.x + b.x) * cross,
+                y + (a.y + b.y) * cross,
+            )
+        });
+        if area.abs() > f32::EPSILON {
+            return Some(Vec2::new((
+                first.x + x / (3.0 * area),
+                first.y + y / (3.0 * area),
+            )));
+        }
+
+        let count = outline.len() as f32;
+        let (x, y) = outline
+            .iter()
+            .fold((0.0, 0.0), |(x, y), point| (x + point.x, y + point.y));
+        Some(Vec2::new((x / count, y / count)))
+    }
+
+    /// Positions of the transform handles: the move handle on the centroid,
+    /// the rotate handle above the outline and the scale handle past its
+    /// lower right corner. `size` is the handle radius in editor space.
+    pub fn transform_handles(&self, size: f32) -> Vec<(TransformHandle, Vec2)> {
+        let outline = self.triangulator.outline();
+        let Some(centroid) = self.centroid().filter(|_| outline.len() >= 3) else {
+            return vec![];
+        };
+        let (min, max) = outline
+            .iter()
+            .fold((outline[0], outline[0]), |(min, max), point| {
+                (
+                    Vec2::new((min.x.min(point.x), min.y.min(point.y))),
+                    Vec2::new((max.x.max(point.x), max.y.max(point.y))),
+                )
+            });
+
+        vec![
+            (TransformHandle::Move, centroid),
+            (
+                TransformHandle::Rotate,
+                Vec2::new((centroid.x, max.y + 3.0 * size)),
+            ),
+            (
+                TransformHandle::Scale,
+                Vec2::new((max.x + 2.0 * size, min.y - 2.0 * size)),
+            ),
+        ]
+    }
+
+    pub fn hit_transform_handle(&self, pos: (f32, f32), radius: f32) -> Option<TransformHandle> {
+        let pos = Vec2::new(pos);
+        self.transform_handles(radius)
+            .into_iter()
+            .find(|(_, handle)| (*handle - pos) * (*handle - pos) <= radius * radius)
+            .map(|(handle, _)| handle)
+    }
+
+    /// Previews a transform of the whole selection without touching its
+    /// points, see `apply_transform`.
+    pub fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.world_matrix = transform * Matrix::translate(0.0, 0.0, -0.1);
+    }
+
+    /// Bakes the previewed transform into the points, returning the new
+    /// outline and holes.
+    pub fn apply_transform(&mut self) -> (Vec<Vec2>, Vec<Vec<Vec2>>) {
+        let transform = self.transform;
+        let outline: Vec<_> = self
+            .triangulator
+            .outline()
+            .iter()
+            .map(|point| transform * *point)
+            .collect();
+        let holes: Vec<Vec<_>> = self
+            .triangulator
+            .get_holes()
+            .iter()
+            .map(|hole| hole.iter().map(|point| transform * *point).collect())
+            .collect();
+
+        self.triangulator =
+            Triangulator::from_outline(&outline, &holes).with_mode(self.triangulator.mode());
+        self.set_transform(Matrix::ident());
+        self.update_mesh();
+        (outline, holes)
+    }
+
+    fn handle_mesh(inner: f32, outer: f32, res: u32, color: Color) -> Mesh<VertexPC, 3> {
+        let (mut vertices, indices) = VertexPC::ring(inner, outer, res);
+        vertices
+            .vertices
+            .iter_mut()
+            .for_each(|vertex| vertex.col = color);
+        Mesh::build(vertices, indices, MeshType::Triangles)
+    }
+
+    fn rings(&self) -> impl Iterator<Item = (Option<usize>, &[Vec2])> {
+        [(None, self.triangulator.outline())].into_iter().chain(
+            self.triangulator
+                .get_holes()
+                .iter()
+                .enumerate()
+                .map(|(hole, ring)| (Some(hole), ring.as_slice())),
+        )
+    }
+
+    /// Midpoints of every edge along with the handle a point inserted there
+    /// would get.
+    fn midpoints(&self) -> impl Iterator<Item = (PointHandle, Vec2)> + '_ {
+        self.rings()
+            .filter(|(_, ring)| ring.len() >= 2)
+            .flat_map(|(hole, ring)| {
+                ring.iter().enumerate().map(move |(index, point)| {
+                    let next = ring[(index + 1) % ring.len()];
+                    let handle = PointHandle {
+                        hole,
+                        index: index + 1,
+                    };
+                    let midpoint = Vec2::new(((point.x + next.x) / 2.0, (point.y + next.y) / 2.0));
+                    (handle, midpoint)
+                })
+            })
+    }
+
     fn update_mesh(&mut self) {
-        match self.triangulator.triangulate() {
-            Some((vertices, indices)) => {
-                self.selection_area.v_buffer.vertices = vertices
-                    .iter()
-                    .map(|Vec2 { x, y }| VertexPC {
-                        pos: Position(*x, *y),
-                        col: Color(0.2, 0.5, 0.9, 0.5),
-                    })
-                    .collect();
-                self.selection_area.i_buffer.indices = indices;
-                self.selection_area.load();
+        let Some(update) = self.triangulator.triangulate_incremental() else {
+            return;
+        };
+        let (vertices, indices) = self.triangulator.mesh();
+        let vertex = |Vec2 { x, y }: &Vec2| VertexPC {
+            pos: Position(*x, *y),
+            col: self.color,
+        };
+
+        let area = &mut self.selection_area;
+        match update {
+            MeshUpdate::Full => {
+                area.v_buffer.vertices = vertices.iter().map(vertex).collect();
+                area.i_buffer.indices = indices.to_vec();
+                area.load();
+            }
+            MeshUpdate::Partial {
+                vertices: changed_vertices,
+                triangles: changed_triangles,
+            } => {
+                for i in &changed_vertices {
+                    area.v_buffer.vertices[*i] = vertex(&vertices[*i]);
+                }
+                for i in &changed_triangles {
+                    area.i_buffer.indices[*i] = indices[*i];
+                }
+                area.load_vertices(&changed_vertices);
+                area.load_indices(&changed_triangles);
             }
-            None => {}
         }
     }
 }
 
-impl<'a> UiElementInner for UiImageSelection<'a> {
-    fn on_mouse_event(&mut self, _pos: (f32, f32), _event: MouseEvent) -> bool {
+impl UiElementInner for UiImageSelection {
+    fn on_event(&mut self, _pos: (f32, f32), _event: InputEvent) -> bool {
         false
     }
 
@@ -76,22 +403,39 @@ impl<'a> UiElementInner for UiImageSelection<'a> {
             .set_matrix("world\x00", context.get_matrix())
         {
             self.selection_area.render();
+            if self.edit_mode.get() == EditMode::Preview {
+                return;
+            }
 
             let world_mat = *context.get_matrix();
-            let aspect_mat = context.get_aspect_matrix();
+            let aspect_mat = *context.get_aspect_matrix();
+            let render_at = |point: Vec2, mesh: &Mesh<VertexPC, 3>| {
+                let translation = world_mat * point;
+                let mat = aspect_mat * Matrix::translate(translation.x, translation.y, 0.0);
 
-            self.triangulator
-                .get_points()
-                .iter()
-                .rev()
-                .skip(1)
-                .for_each(|point| {
-                    let translation = world_mat * *point;
-                    let mat = *aspect_mat * Matrix::translate(translation.x, translation.y, 0.0);
-
-                    context.col_shader.set_matrix("world\x00", &mat);
-                    self.selection_point.render();
-                });
+                context.col_shader.set_matrix("world\x00", &mat);
+                mesh.render();
+            };
+
+            self.rings()
+                .flat_map(|(_, ring)| ring.iter())
+                .for_each(|point| render_at(*point, &self.selection_point));
+            if self.selected {
+                self.midpoints()
+                    .for_each(|(_, midpoint)| render_at(midpoint, &self.midpoint));
+            }
+            if let Some(point) = self.selected_point.and_then(|handle| self.point(handle)) {
+                render_at(point, &self.highlight_point);
+            }
+            if self.selected {
+                self.transform_handles(self.handle_radius.get())
+                    .into_iter()
+                    .for_each(|(handle, point)| match handle {
+                        TransformHandle::Move => render_at(point, &self.move_handle),
+                        TransformHandle::Rotate => render_at(point, &self.rotate_handle),
+                        TransformHandle::Scale => render_at(point, &self.scale_handle),
+                    });
+            }
         }
     }
 
@@ -132,8 +476,12 @@ impl<'a> UiElementInner for UiImageSelection<'a> {
         }
     }
 
-    fn set_position(&mut self, _pos: (f32, f32)) {
-        panic!("God left me unfinished");
+    /// Moves the selection so that its centroid lands on `pos`, until the
+    /// transform is applied.
+    fn set_position(&mut self, (x, y): (f32, f32)) {
+        if let Some(centroid) = self.centroid() {
+            self.set_transform(Matrix::translate(x - centroid.x, y - centroid.y, 0.0));
+        }
     }
 
     fn get_children<'b>(&'b self) -> Box<dyn Iterator<Item = &dyn UiElement> + 'b> {
@@ -0,0 +1,103 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    input::{InputEvent, MouseButton},
+    matrix::Matrix,
+    vec::Vec2,
+};
+
+use super::{
+    mesh::Mesh,
+    shader_context::ShaderContext,
+    vertex::{Color, MeshGenerator, MeshType, VertexPC},
+    BoundingRect, EditMode, UiElement, UiElementInner,
+};
+
+/// Button flipping the shared edit mode between Edit and Preview, coloured
+/// after the current mode.
+pub struct ModeToggle {
+    edit_mode: Rc<Cell<EditMode>>,
+    edit_button: Mesh<VertexPC, 3>,
+    preview_button: Mesh<VertexPC, 3>,
+    bounding_rect: BoundingRect,
+    world_matrix: Matrix,
+    children: Vec<Box<dyn UiElement>>,
+}
+
+impl ModeToggle {
+    /// `pos` is relative to the parent at `origin`, which events are not.
+    pub fn new(origin: Vec2, pos: Vec2, size: Vec2, edit_mode: Rc<Cell<EditMode>>) -> Self {
+        let button = |color: Color| {
+            let (mut vertices, indices) = VertexPC::quad(size.x, size.y);
+            vertices
+                .vertices
+                .iter_mut()
+                .for_each(|VertexPC { col, .. }| *col = color);
+            Mesh::build(vertices, indices, MeshType::Triangles)
+        };
+
+        ModeToggle {
+            edit_mode,
+            edit_button: button(Color(0.9, 0.6, 0.2, 1.0)),
+            preview_button: button(Color(0.5, 0.5, 0.55, 1.0)),
+            bounding_rect: BoundingRect::new(origin + pos, size),
+            world_matrix: Matrix::translate(pos.x, pos.y, 0.0),
+            children: vec![],
+        }
+    }
+}
+
+impl UiElementInner for ModeToggle {
+    fn on_event(&mut self, _pos: (f32, f32), event: InputEvent) -> bool {
+        match event {
+            InputEvent::MousePress {
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.edit_mode.set(match self.edit_mode.get() {
+                    EditMode::Edit => EditMode::Preview,
+                    EditMode::Preview => EditMode::Edit,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn get_world_matrix(&self) -> &Matrix {
+        &self.world_matrix
+    }
+
+    fn render(&self, context: &mut ShaderContext) {
+        if context
+            .col_shader
+            .set_matrix("world\x00", context.get_matrix())
+        {
+            match self.edit_mode.get() {
+                EditMode::Edit => self.edit_button.render(),
+                EditMode::Preview => self.preview_button.render(),
+            }
+        }
+    }
+
+    fn set_position(&mut self, (x, y): (f32, f32)) {
+        self.bounding_rect.left = x;
+        self.bounding_rect.top = y;
+    }
+
+    fn get_children<'a>(&'a self) -> Box<dyn Iterator<Item = &dyn UiElement> + 'a> {
+        Box::new(self.children.iter().map(|child| &**child))
+    }
+
+    fn get_children_mut<'a>(&'a mut self) -> Box<dyn Iterator<Item = &mut dyn UiElement> + 'a> {
+        Box::new(
+            self.children
+                .iter_mut()
+                .map(|child| &mut **child as &mut dyn UiElement),
+        )
+    }
+
+    fn get_bounding_box(&self) -> BoundingRect {
+        self.bounding_rect
+    }
+}
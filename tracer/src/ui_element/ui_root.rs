@@ -1,53 +1,307 @@
 use crate::{
-    application::{AppError, MouseEvent},
+    annotation::Project,
+    application::AppError,
+    formats::{
+        coco::{self, CocoEncoding},
+        cvat, geojson, labelme,
+        mask::{self, MaskDepth, MaskKind},
+        svg::{self, SvgImage},
+        voc,
+        yolo::{self, YoloFlavour},
+    },
+    input::InputEvent,
+    keymap::Keymap,
     vec::Vec2,
 };
 
 use super::{
-    matrix::Matrix, shader_context::ShaderContext, ui_dropdown::Dropdown, ui_group::UiGroup,
-    ui_image_editor::UiImageEditor, vertex::Color, UiElement,
+    shader_context::ShaderContext, ui_dropdown::Dropdown, ui_group::UiGroup,
+    ui_image_editor::UiImageEditor, ui_mode_toggle::ModeToggle, vertex::Color, UiElement,
 };
 
+const MASK_IMPORT_TOLERANCE: f32 = 1.0;
+const KEYMAP_PATH: &str = "tracer/keymap.toml";
+const WINDOW_SIZE: (u32, u32) = (1200, 800);
+
+/// Annotations to import at startup, each picked on the command line with
+/// `--import-<format> <path>` and applied in the order given.
+/// `--mask-tolerance <pixels>` sets the simplification tolerance of the mask
+/// imports that follow it.
+pub enum Import {
+    Coco(String),
+    Cvat(String),
+    Voc(String),
+    GeoJson(String),
+    Masks { directory: String, tolerance: f32 },
+}
+
+/// Exports to write on exit next to the LabelMe files, each picked on the
+/// command line with `--export-<format> <path>`.
+pub enum Export {
+    Coco(String),
+    CocoRle(String),
+    Cvat(String),
+    Voc(String),
+    ClassMasks(String),
+    InstanceMasks(String),
+    GeoJson(String),
+    Svg(String),
+    SvgEmbedded(String),
+    YoloSegmentation(String),
+    YoloDetection(String),
+}
+
+pub struct Arguments {
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+}
+
+impl Arguments {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Arguments, AppError> {
+        let mut arguments = Arguments {
+            imports: vec![],
+            exports: vec![],
+        };
+        let mut tolerance = MASK_IMPORT_TOLERANCE;
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next().ok_or_else(|| AppError {
+                    error_msg: format!("{} expects a value", flag),
+                })
+            };
+            let import = match flag.as_str() {
+                "--import-coco" => Import::Coco(value()?),
+                "--import-cvat" => Import::Cvat(value()?),
+                "--import-voc" => Import::Voc(value()?),
+                "--import-geojson" => Import::GeoJson(value()?),
+                "--import-masks" => Import::Masks {
+                    directory: value()?,
+                    tolerance,
+                },
+                "--mask-tolerance" => {
+                    let value = value()?;
+                    tolerance = value.parse().map_err(|_| AppError {
+                        error_msg: format!("{} expects a number of pixels, got {}", flag, value),
+                    })?;
+                    continue;
+                }
+                _ => {
+                    let export = match flag.as_str() {
+                        "--export-coco" => Export::Coco(value()?),
+                        "--export-coco-rle" => Export::CocoRle(value()?),
+                        "--export-cvat" => Export::Cvat(value()?),
+                        "--export-voc" => Export::Voc(value()?),
+                        "--export-class-masks" => Export::ClassMasks(value()?),
+                        "--export-instance-masks" => Export::InstanceMasks(value()?),
+                        "--export-geojson" => Export::GeoJson(value()?),
+                        "--export-svg" => Export::Svg(value()?),
+                        "--export-svg-embedded" => Export::SvgEmbedded(value()?),
+                        "--export-yolo-segmentation" => Export::YoloSegmentation(value()?),
+                        "--export-yolo-detection" => Export::YoloDetection(value()?),
+                        _ => {
+                            return Err(AppError {
+                                error_msg: format!("Unknown argument {}", flag),
+                            })
+                        }
+                    };
+                    arguments.exports.push(export);
+                    continue;
+                }
+            };
+            arguments.imports.push(import);
+        }
+        Ok(arguments)
+    }
+}
+
 pub struct UiRoot {
     context: ShaderContext,
     toolbox: Box<dyn UiElement>,
-    image_editor: Box<dyn UiElement>,
+    image_editor: UiImageEditor,
 }
 
 impl UiRoot {
-    pub fn build() -> Result<Self, AppError> {
+    pub fn build(imports: &[Import]) -> Result<Self, AppError> {
+        let labels = vec!["jp2", "gmd"];
+        let mut project = Project::new(&labels);
+        project.author = std::env::var("USER").unwrap_or_default();
+
+        let context = ShaderContext::build(WINDOW_SIZE).expect("Failed to compile shaders");
+        let image_editor = UiImageEditor::new((0.0, 0.0), (0.99, 0.99), (1.2 / 0.8, 1.0), project)
+            .with_keymap(Keymap::load(KEYMAP_PATH)?)
+            .with_window_size(WINDOW_SIZE);
+
+        let mut image_editor = image_editor.load_image("tracer/images/boomer.jpg")?;
+        // Imports add to whatever the LabelMe files next to the images held.
+        for import in imports {
+            image_editor = match import {
+                Import::Coco(path) => image_editor.import_coco(path)?,
+                Import::Cvat(path) => image_editor.import_cvat(path)?,
+                Import::Voc(directory) => image_editor.import_voc(directory)?,
+                Import::GeoJson(directory) => image_editor.import_geojson(directory)?,
+                Import::Masks {
+                    directory,
+                    tolerance,
+                } => image_editor.import_masks(directory, *tolerance)?,
+            };
+        }
+
+        let toolbox_pos = Vec2::new((1.25, 0.0));
         let dropdown = Dropdown::new(
+            toolbox_pos,
             Vec2::new((0.0, 0.125)),
             Vec2::new((0.15, 0.04)),
-            vec!["jp2", "gmd"],
+            image_editor.label_names(),
+            image_editor.current_label(),
+        );
+        let mode_toggle = ModeToggle::new(
+            toolbox_pos,
+            Vec2::new((0.0, -0.125)),
+            Vec2::new((0.15, 0.04)),
+            image_editor.edit_mode(),
         );
 
         let toolbox = UiGroup::new(
-            Vec2::new((1.25, 0.0)),
+            toolbox_pos,
             Vec2::new((0.25, 0.5)),
             2,
             Color(0.1, 0.3, 0.7, 1.0),
-            vec![Box::new(dropdown)],
+            vec![Box::new(dropdown), Box::new(mode_toggle)],
         );
 
-        let image_editor = UiImageEditor::new((0.0, 0.0), (0.99, 0.99), (1.2 / 0.8, 1.0));
-        let image_editor = Box::new(image_editor.load_image("tracer/images/boomer.jpg")?);
-
         Ok(UiRoot {
-            context: ShaderContext::build((1200, 800)).expect("Failed to compile shaders"),
+            context,
             toolbox,
             image_editor,
         })
     }
 
+    pub fn should_close(&self) -> bool {
+        self.image_editor.quit_requested()
+    }
+
     pub fn render(&mut self) {
         self.image_editor.render(&mut self.context);
         self.toolbox.render(&mut self.context);
     }
 
-    pub fn handle(&mut self, pos: (f32, f32), event: MouseEvent) {
-        if !self.toolbox.handle_mouse_event(pos, event) {
-            self.image_editor.handle_mouse_event(pos, event);
+    pub fn save(&self) -> Result<(), AppError> {
+        labelme::save(self.image_editor.project())?;
+        Ok(())
+    }
+
+    /// Runs every requested export even when some fail, the error lists
+    /// each failure by path.
+    pub fn export(&self, exports: &[Export]) -> Result<(), AppError> {
+        let project = self.image_editor.project();
+        let errors: Vec<String> = exports
+            .iter()
+            .filter_map(|export| {
+                let (path, result) = match export {
+                    Export::Coco(path) => (path, coco::save(project, path, CocoEncoding::Polygons)),
+                    Export::CocoRle(path) => (path, coco::save(project, path, CocoEncoding::Rle)),
+                    Export::Cvat(path) => (
+                        path,
+                        cvat::save(project, path)
+                            .map(|dropped| report_dropped_holes(path, dropped)),
+                    ),
+                    Export::Voc(path) => (
+                        path,
+                        voc::save(project, path).map(|dropped| report_dropped_holes(path, dropped)),
+                    ),
+                    Export::ClassMasks(path) => (
+                        path,
+                        mask::save(project, path, MaskKind::Class, MaskDepth::Eight),
+                    ),
+                    Export::InstanceMasks(path) => (
+                        path,
+                        mask::save(project, path, MaskKind::Instance, MaskDepth::Sixteen),
+                    ),
+                    Export::GeoJson(path) => (path, geojson::save(project, path)),
+                    Export::Svg(path) => (path, svg::save(project, path, SvgImage::Linked)),
+                    Export::SvgEmbedded(path) => {
+                        (path, svg::save(project, path, SvgImage::Embedded))
+                    }
+                    Export::YoloSegmentation(path) => (
+                        path,
+                        yolo::save(project, path, YoloFlavour::Segmentation)
+                            .map(|dropped| report_dropped_holes(path, dropped)),
+                    ),
+                    Export::YoloDetection(path) => (
+                        path,
+                        yolo::save(project, path, YoloFlavour::Detection)
+                            .map(|dropped| report_dropped_holes(path, dropped)),
+                    ),
+                };
+                result
+                    .err()
+                    .map(|err| format!("Export to {} failed: {}", path, err.error_msg))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError {
+                error_msg: errors.join("\n"),
+            })
+        }
+    }
+
+    pub fn handle(&mut self, pos: (f32, f32), event: InputEvent) {
+        if self.toolbox.handle_event(pos, event) {
+            // The mode toggle and the label picker change the shared state
+            // behind the editor's back.
+            self.image_editor.sync_edit_mode();
+            self.image_editor.sync_label();
+        } else {
+            self.image_editor.handle_event(pos, event);
         }
     }
 }
+
+fn report_dropped_holes(path: &str, dropped: usize) {
+    if dropped > 0 {
+        eprintln!(
+            "Exported {} annotations to {} without their holes, the format cannot hold them",
+            dropped, path
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Arguments, AppError> {
+        Arguments::parse(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn export_flags_pick_the_encoding() {
+        let arguments = parse(&["--export-coco", "a.json", "--export-coco-rle", "b.json"]).unwrap();
+        assert!(matches!(
+            arguments.exports.as_slice(),
+            [Export::Coco(polygons), Export::CocoRle(rle)] if polygons == "a.json" && rle == "b.json"
+        ));
+        assert!(arguments.imports.is_empty());
+
+        assert!(parse(&["--export-coco-rle"]).is_err());
+        assert!(parse(&["--export-rle", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn svg_images_are_linked_unless_embedded() {
+        let arguments = parse(&[
+            "--export-svg",
+            "linked",
+            "--export-svg-embedded",
+            "embedded",
+        ])
+        .unwrap();
+        assert!(matches!(
+            arguments.exports.as_slice(),
+            [Export::Svg(linked), Export::SvgEmbedded(embedded)]
+                if linked == "linked" && embedded == "embedded"
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use std::ops;
 
-#[derive(Clone, Copy, Debug)]
+pub mod predicates;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
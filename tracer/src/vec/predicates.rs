@@ -0,0 +1,324 @@
+use super::Vec2;
+
+const EPSILON: f64 = f64::EPSILON * 0.5;
+const CCW_ERRBOUND_A: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const ICC_ERRBOUND_A: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+
+/// Positive when `a`, `b`, `c` are in counter-clockwise order, negative when
+/// clockwise and exactly zero when collinear.
+pub fn orient2d(a: Vec2, b: Vec2, c: Vec2) -> f64 {
+    let (a, b, c) = (to_f64(a), to_f64(b), to_f64(c));
+
+    let det_left = (a.0 - c.0) * (b.1 - c.1);
+    let det_right = (a.1 - c.1) * (b.0 - c.0);
+    let det = det_left - det_right;
+
+    let det_sum = det_left.abs() + det_right.abs();
+    if det.abs() >= CCW_ERRBOUND_A * det_sum {
+        return det;
+    }
+
+    orient2d_exact(a, b, c)
+}
+
+/// Positive when `d` lies inside the circle through the counter-clockwise
+/// triangle `a`, `b`, `c`, negative when outside and exactly zero when the
+/// four points are cocircular.
+pub fn incircle(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> f64 {
+    let (a, b, c, d) = (to_f64(a), to_f64(b), to_f64(c), to_f64(d));
+
+    let (adx, ady) = (a.0 - d.0, a.1 - d.1);
+    let (bdx, bdy) = (b.0 - d.0, b.1 - d.1);
+    let (cdx, cdy) = (c.0 - d.0, c.1 - d.1);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+    if det.abs() >= ICC_ERRBOUND_A * permanent {
+        return det;
+    }
+
+    incircle_exact(a, b, c, d)
+}
+
+fn to_f64(point: Vec2) -> (f64, f64) {
+    (point.x as f64, point.y as f64)
+}
+
+fn orient2d_exact(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    let (acx, acy) = (two_diff(a.0, c.0), two_diff(a.1, c.1));
+    let (bcx, bcy) = (two_diff(b.0, c.0), two_diff(b.1, c.1));
+
+    let det = expansion_diff(
+        &expansion_product(&acx, &bcy),
+        &expansion_product(&acy, &bcx),
+    );
+    most_significant(&det)
+}
+
+fn incircle_exact(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> f64 {
+    let (adx, ady) = (two_diff(a.0, d.0), two_diff(a.1, d.1));
+    let (bdx, bdy) = (two_diff(b.0, d.0), two_diff(b.1, d.1));
+    let (cdx, cdy) = (two_diff(c.0, d.0), two_diff(c.1, d.1));
+
+    let lift =
+        |x: &[f64], y: &[f64]| expansion_sum(&expansion_product(x, x), &expansion_product(y, y));
+    let minor = |x1: &[f64], y1: &[f64], x2: &[f64], y2: &[f64]| {
+        expansion_diff(&expansion_product(x1, y2), &expansion_product(x2, y1))
+    };
+
+    let a_term = expansion_product(&lift(&adx, &ady), &minor(&bdx, &bdy, &cdx, &cdy));
+    let b_term = expansion_product(&lift(&bdx, &bdy), &minor(&cdx, &cdy, &adx, &ady));
+    let c_term = expansion_product(&lift(&cdx, &cdy), &minor(&adx, &ady, &bdx, &bdy));
+
+    let det = expansion_sum(&expansion_sum(&a_term, &b_term), &c_term);
+    most_significant(&det)
+}
+
+// Expansion arithmetic after Shewchuk, "Adaptive Precision Floating-Point
+// Arithmetic and Fast Robust Geometric Predicates". Expansions are stored
+// with increasing magnitude and without zero components.
+
+fn fast_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    (x, b - b_virtual)
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    let (b_roundoff, a_roundoff) = (b - b_virtual, a - a_virtual);
+    (x, a_roundoff + b_roundoff)
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+fn two_diff(a: f64, b: f64) -> Vec<f64> {
+    let x = a - b;
+    let b_virtual = a - x;
+    let a_virtual = x + b_virtual;
+    let (b_roundoff, a_roundoff) = (b_virtual - b, a - a_virtual);
+    eliminate_zeros(&[a_roundoff + b_roundoff, x])
+}
+
+fn eliminate_zeros(expansion: &[f64]) -> Vec<f64> {
+    expansion.iter().copied().filter(|e| *e != 0.0).collect()
+}
+
+fn grow_expansion(expansion: &[f64], b: f64) -> Vec<f64> {
+    let mut result = Vec::with_capacity(expansion.len() + 1);
+    let q = expansion.iter().fold(b, |q, e| {
+        let (q, h) = two_sum(q, *e);
+        if h != 0.0 {
+            result.push(h);
+        }
+        q
+    });
+    if q != 0.0 {
+        result.push(q);
+    }
+    result
+}
+
+fn expansion_sum(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(e.to_vec(), |sum, component| {
+        grow_expansion(&sum, *component)
+    })
+}
+
+fn expansion_diff(e: &[f64], f: &[f64]) -> Vec<f64> {
+    let negated: Vec<_> = f.iter().map(|component| -component).collect();
+    expansion_sum(e, &negated)
+}
+
+fn scale_expansion(expansion: &[f64], b: f64) -> Vec<f64> {
+    let mut result = Vec::with_capacity(expansion.len() * 2);
+    let Some((first, rest)) = expansion.split_first() else {
+        return result;
+    };
+
+    let (mut q, h) = two_product(*first, b);
+    if h != 0.0 {
+        result.push(h);
+    }
+    for e in rest {
+        let (product_hi, product_lo) = two_product(*e, b);
+        let (sum, h) = two_sum(q, product_lo);
+        if h != 0.0 {
+            result.push(h);
+        }
+        let (next_q, h) = fast_two_sum(product_hi, sum);
+        if h != 0.0 {
+            result.push(h);
+        }
+        q = next_q;
+    }
+    if q != 0.0 {
+        result.push(q);
+    }
+    result
+}
+
+fn expansion_product(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(vec![], |product, component| {
+        expansion_sum(&product, &scale_expansion(e, *component))
+    })
+}
+
+fn most_significant(expansion: &[f64]) -> f64 {
+    expansion.last().copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    const GRID: f32 = 1.0 / 1024.0;
+
+    fn grid_point((x, y): (i32, i32)) -> Vec2 {
+        Vec2::new((x as f32 * GRID, y as f32 * GRID))
+    }
+
+    fn exact_orient2d(a: (i32, i32), b: (i32, i32), c: (i32, i32)) -> i128 {
+        let (acx, acy) = ((a.0 - c.0) as i128, (a.1 - c.1) as i128);
+        let (bcx, bcy) = ((b.0 - c.0) as i128, (b.1 - c.1) as i128);
+        acx * bcy - acy * bcx
+    }
+
+    fn exact_incircle(a: (i32, i32), b: (i32, i32), c: (i32, i32), d: (i32, i32)) -> i128 {
+        let delta = |p: (i32, i32)| ((p.0 - d.0) as i128, (p.1 - d.1) as i128);
+        let (a, b, c) = (delta(a), delta(b), delta(c));
+        let lift = |p: (i128, i128)| p.0 * p.0 + p.1 * p.1;
+
+        lift(a) * (b.0 * c.1 - c.0 * b.1)
+            + lift(b) * (c.0 * a.1 - a.0 * c.1)
+            + lift(c) * (a.0 * b.1 - b.0 * a.1)
+    }
+
+    fn sign(value: f64) -> i128 {
+        if value > 0.0 {
+            1
+        } else if value < 0.0 {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn coordinate() -> impl Strategy<Value = (i32, i32)> {
+        prop_oneof![
+            (-4..4, -4..4),
+            (-(1 << 20)..(1 << 20), -(1 << 20)..(1 << 20)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn orient2d_matches_exact_sign(a in coordinate(), b in coordinate(), c in coordinate()) {
+            let orientation = orient2d(grid_point(a), grid_point(b), grid_point(c));
+            prop_assert_eq!(sign(orientation), exact_orient2d(a, b, c).signum());
+        }
+
+        #[test]
+        fn orient2d_is_antisymmetric(a in coordinate(), b in coordinate(), c in coordinate()) {
+            let (a, b, c) = (grid_point(a), grid_point(b), grid_point(c));
+            prop_assert_eq!(sign(orient2d(a, b, c)), -sign(orient2d(b, a, c)));
+            prop_assert_eq!(sign(orient2d(a, b, c)), sign(orient2d(b, c, a)));
+        }
+
+        #[test]
+        fn collinear_points_are_degenerate(
+            origin in coordinate(),
+            direction in (-64..64, -64..64),
+            steps in (-64..64, -64..64),
+        ) {
+            let along = |t: i32| (origin.0 + direction.0 * t, origin.1 + direction.1 * t);
+            let (a, b, c) = (along(0), along(steps.0), along(steps.1));
+            prop_assert_eq!(orient2d(grid_point(a), grid_point(b), grid_point(c)), 0.0);
+        }
+
+        #[test]
+        fn duplicate_points_are_degenerate(a in coordinate(), b in coordinate(), c in coordinate()) {
+            let (a, b, c) = (grid_point(a), grid_point(b), grid_point(c));
+            prop_assert_eq!(orient2d(a, a, b), 0.0);
+            prop_assert_eq!(orient2d(a, b, b), 0.0);
+            prop_assert_eq!(incircle(a, a, b, c), 0.0);
+            prop_assert_eq!(incircle(a, b, c, c), 0.0);
+        }
+
+        #[test]
+        fn incircle_matches_exact_sign(
+            a in coordinate(),
+            b in coordinate(),
+            c in coordinate(),
+            d in coordinate(),
+        ) {
+            let inside = incircle(grid_point(a), grid_point(b), grid_point(c), grid_point(d));
+            prop_assert_eq!(sign(inside), exact_incircle(a, b, c, d).signum());
+        }
+
+        #[test]
+        fn exact_fallbacks_match_exact_sign(
+            a in coordinate(),
+            b in coordinate(),
+            c in coordinate(),
+            d in coordinate(),
+        ) {
+            let (pa, pb, pc, pd) = (
+                to_f64(grid_point(a)),
+                to_f64(grid_point(b)),
+                to_f64(grid_point(c)),
+                to_f64(grid_point(d)),
+            );
+            prop_assert_eq!(sign(orient2d_exact(pa, pb, pc)), exact_orient2d(a, b, c).signum());
+            prop_assert_eq!(
+                sign(incircle_exact(pa, pb, pc, pd)),
+                exact_incircle(a, b, c, d).signum()
+            );
+        }
+
+        #[test]
+        fn nearly_collinear_points_match_exact_sign(i in 0..256, j in 0..256) {
+            let ulp = f32::EPSILON * 0.5;
+            let a = Vec2::new((0.5 + i as f32 * ulp, 0.5 + j as f32 * ulp));
+            let (b, c) = (Vec2::new((12.0, 12.0)), Vec2::new((24.0, 24.0)));
+
+            let scaled = |v: f32| (v / ulp) as i32;
+            let exact = exact_orient2d(
+                (scaled(a.x), scaled(a.y)),
+                (scaled(b.x), scaled(b.y)),
+                (scaled(c.x), scaled(c.y)),
+            );
+            prop_assert_eq!(sign(orient2d(a, b, c)), exact.signum());
+        }
+    }
+
+    #[test]
+    fn cocircular_points_are_degenerate() {
+        let (a, b, c, d) = (
+            Vec2::new((1.0, 0.0)),
+            Vec2::new((0.0, 1.0)),
+            Vec2::new((-1.0, 0.0)),
+            Vec2::new((0.0, -1.0)),
+        );
+        assert_eq!(incircle(a, b, c, d), 0.0);
+        assert!(incircle(a, b, c, Vec2::new((0.0, 0.0))) > 0.0);
+        assert!(incircle(a, b, c, Vec2::new((2.0, 2.0))) < 0.0);
+    }
+}
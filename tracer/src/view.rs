@@ -0,0 +1,227 @@
+use crate::{annotation::ImageFrame, matrix::Matrix, vec::Vec2};
+
+/// Part of the image in editor units that panning keeps inside the editor.
+const PAN_MARGIN: f32 = 0.1;
+/// Zoom range scrolling stays in, as screen units per editor unit.
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 50.0;
+
+/// Pan and zoom of the image editor. The matrix maps editor space, which the
+/// image and its selections live in, onto the cursor's screen space, where
+/// the window height spans two units.
+#[derive(Clone, Copy)]
+pub struct View {
+    matrix: Matrix,
+    pos: (f32, f32),
+    size: (f32, f32),
+    sensitivity: f32,
+    pixels_per_unit: f32,
+}
+
+impl View {
+    /// `pos` and `size` are the center and half size of the editor in screen
+    /// space.
+    pub fn new(pos: (f32, f32), size: (f32, f32)) -> Self {
+        View {
+            matrix: Matrix::translate(pos.0, pos.1, 0.0),
+            pos,
+            size,
+            sensitivity: 0.1,
+            pixels_per_unit: 400.0,
+        }
+    }
+
+    /// Window size in pixels, which the 1:1 pixel view depends on.
+    pub fn with_window_size(mut self, (_, height): (u32, u32)) -> Self {
+        self.pixels_per_unit = height as f32 / 2.0;
+        self
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    /// Screen units an editor unit spans along x and y.
+    pub fn scale(&self) -> (f32, f32) {
+        self.matrix.axis_scale()
+    }
+
+    /// Maps a cursor position into editor space through the inverse of the
+    /// view matrix. The cursor is already in screen space, the aspect matrix
+    /// the render stack starts from only takes that on to clip space.
+    /// Positions pass through unchanged while the view is singular.
+    pub fn to_editor(&self, pos: (f32, f32)) -> (f32, f32) {
+        match self.matrix.inverse() {
+            Some(inverse) => {
+                let pos = inverse * Vec2::new(pos);
+                (pos.x, pos.y)
+            }
+            None => pos,
+        }
+    }
+
+    /// Zooms by `amount` scroll steps about the cursor at `(x, y)`, keeping
+    /// the point under it in place.
+    pub fn zoom(&mut self, frame: &ImageFrame, (x, y): (f32, f32), amount: f32) {
+        let (scale_x, scale_y) = self.scale();
+        let factor = 1.0 + amount * self.sensitivity;
+
+        if (scale_x.min(scale_y) > MIN_ZOOM || factor > 1.0)
+            && (scale_x.max(scale_y) < MAX_ZOOM || factor < 1.0)
+        {
+            self.matrix = self.matrix
+                * Matrix::translate(-x, -y, 0.0)
+                * Matrix::scale(factor)
+                * Matrix::translate(x, y, 0.0);
+            self.clamp(frame);
+        }
+    }
+
+    pub fn pan(&mut self, frame: &ImageFrame, (dx, dy): (f32, f32)) {
+        self.matrix = self.matrix * Matrix::translate(dx, dy, 0.0);
+        self.clamp(frame);
+    }
+
+    pub fn zoom_to_fit(&mut self) {
+        self.matrix = Matrix::translate(self.pos.0, self.pos.1, 0.0);
+    }
+
+    /// Centers the image zoomed so that one image pixel covers one screen
+    /// pixel.
+    pub fn actual_size(&mut self, frame: &ImageFrame) {
+        let editor_units_per_pixel = 2.0 * frame.half_extent.0 / frame.resolution.0;
+        let scale = 1.0 / (editor_units_per_pixel * self.pixels_per_unit);
+        self.matrix = Matrix::scale(scale) * Matrix::translate(self.pos.0, self.pos.1, 0.0);
+    }
+
+    pub fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+        self.matrix = Matrix::translate(pos.0, pos.1, 0.0) * self.matrix;
+    }
+
+    /// Keeps at least `PAN_MARGIN` of the image inside the editor on both
+    /// axes, so that it can never be panned out of sight.
+    fn clamp(&mut self, frame: &ImageFrame) {
+        let (scale_x, scale_y) = self.scale();
+        let clamp = |offset: f32, center: f32, viewport: f32, image: f32| {
+            let reach = viewport + image - PAN_MARGIN.min(image);
+            offset.clamp(center - reach, center + reach)
+        };
+
+        let (half_width, half_height) = frame.half_extent;
+        let data = &mut self.matrix.data;
+        data[3][0] = clamp(data[3][0], self.pos.0, self.size.0, half_width * scale_x);
+        data[3][1] = clamp(data[3][1], self.pos.1, self.size.1, half_height * scale_y);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    const WINDOW: (u32, u32) = (1200, 800);
+    const FRAME: ImageFrame = ImageFrame {
+        resolution: (1200.0, 800.0),
+        half_extent: (1.485, 0.99),
+    };
+
+    fn view() -> View {
+        View::new((0.0, 0.0), (0.99, 0.99)).with_window_size(WINDOW)
+    }
+
+    /// Window pixel a point of editor space is drawn at, through the view and
+    /// the aspect matrix the render stack starts from.
+    fn window_pixel(view: &View, point: (f32, f32)) -> (f32, f32) {
+        let mut aspect_matrix = Matrix::ident();
+        aspect_matrix.data[0][0] = WINDOW.1 as f32 / WINDOW.0 as f32;
+        let clip = (view.matrix * aspect_matrix) * Vec2::new(point);
+        (
+            (clip.x + 1.0) * 0.5 * WINDOW.0 as f32,
+            (1.0 - clip.y) * 0.5 * WINDOW.1 as f32,
+        )
+    }
+
+    /// Cursor position the window reports over a window pixel.
+    fn cursor_at((x, y): (f32, f32)) -> (f32, f32) {
+        let (half_width, half_height) = (WINDOW.0 as f32 * 0.5, WINDOW.1 as f32 * 0.5);
+        (
+            (x - half_width) / half_height,
+            (half_height - y) / half_height,
+        )
+    }
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32), tolerance: f32) {
+        assert!(
+            (actual.0 - expected.0).abs() < tolerance && (actual.1 - expected.1).abs() < tolerance,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_cursor() {
+        let mut view = view();
+        let cursor = (0.3, -0.2);
+        let before = view.to_editor(cursor);
+
+        view.zoom(&FRAME, cursor, 3.0);
+        assert_close(view.to_editor(cursor), before, 1e-5);
+        assert_close(view.scale(), (1.3, 1.3), 1e-5);
+    }
+
+    #[test]
+    fn panning_keeps_part_of_the_image_in_view() {
+        let mut view = view();
+        view.pan(&FRAME, (100.0, -100.0));
+
+        let left_edge = view.to_editor(cursor_at(window_pixel(&view, (-1.485, 0.0))));
+        assert_close(left_edge, (-1.485, 0.0), 1e-4);
+        assert!(view.matrix.data[3][0] - 1.485 <= 0.99 - PAN_MARGIN + 1e-5);
+        assert!(view.matrix.data[3][1] + 0.99 >= -0.99 + PAN_MARGIN - 1e-5);
+    }
+
+    #[test]
+    fn actual_size_maps_image_pixels_onto_window_pixels() {
+        let mut view = view();
+        view.zoom(&FRAME, (0.5, 0.5), 4.0);
+        view.actual_size(&FRAME);
+
+        let editor_units_per_pixel = 2.0 * FRAME.half_extent.0 / FRAME.resolution.0;
+        let (x0, y0) = window_pixel(&view, (0.0, 0.0));
+        let (x1, y1) = window_pixel(&view, (10.0 * editor_units_per_pixel, 0.0));
+        assert_close((x1 - x0, y1 - y0), (10.0, 0.0), 1e-3);
+        assert_close(window_pixel(&view, (0.0, 0.0)), (600.0, 400.0), 1e-3);
+    }
+
+    proptest! {
+        // Points drawn through the view and reported back by the window as
+        // cursor positions must map onto themselves at any zoom and pan.
+        #[test]
+        fn cursor_positions_round_trip_through_the_view(
+            steps in proptest::collection::vec(
+                (any::<bool>(), (-1.5f32..1.5, -1.0f32..1.0), -3.0f32..3.0),
+                0..40,
+            ),
+            point in (-1.485f32..1.485, -0.99f32..0.99),
+        ) {
+            let mut view = view();
+            for (zoom, (x, y), amount) in steps {
+                match zoom {
+                    true => view.zoom(&FRAME, (x, y), amount),
+                    false => view.pan(&FRAME, (x * amount, y * amount)),
+                }
+            }
+
+            let mapped = view.to_editor(cursor_at(window_pixel(&view, point)));
+            let tolerance = 1e-4 * (1.0 + 1.0 / view.scale().0);
+            prop_assert!(
+                (mapped.0 - point.0).abs() < tolerance && (mapped.1 - point.1).abs() < tolerance,
+                "{:?} vs {:?}",
+                mapped,
+                point
+            );
+        }
+    }
+}
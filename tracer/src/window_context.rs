@@ -1,15 +1,25 @@
 use std::sync::mpsc::Receiver;
 
 use glad_gl::gl;
-use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
+use glfw::{Action, Context, Glfw, Window, WindowEvent};
 
-use crate::application::{Application, MouseEvent};
+use crate::{
+    application::Application,
+    input::{self, InputEvent, Modifiers, MouseButton},
+};
 
 pub struct WindowContext<'a> {
     context: Glfw,
     window: Window,
     event_channel: Receiver<(f64, WindowEvent)>,
     application: &'a mut dyn Application,
+    input: InputState,
+}
+
+#[derive(Default)]
+struct InputState {
+    cursor: (f32, f32),
+    held_buttons: Vec<MouseButton>,
 }
 
 impl<'a> WindowContext<'a> {
@@ -28,6 +38,7 @@ impl<'a> WindowContext<'a> {
         };
 
         window.set_key_polling(true);
+        window.set_char_polling(true);
         window.set_mouse_button_polling(true);
         window.set_cursor_pos_polling(true);
         window.set_scroll_polling(true);
@@ -48,29 +59,33 @@ impl<'a> WindowContext<'a> {
             window,
             event_channel: events,
             application,
+            input: InputState::default(),
         })
     }
 
     pub fn run(&mut self) {
         self.application.on_init();
 
-        while !self.window.should_close() {
+        while !self.window.should_close() && !self.application.should_close() {
             self.context.poll_events();
             self.handle_events();
             self.application.render();
             self.window.swap_buffers();
         }
+
+        self.application.on_exit();
     }
 
     fn handle_events(&mut self) {
         glfw::flush_messages(&self.event_channel).for_each(|(_time, event)| {
-            Self::handle_window_event(self.application, &mut self.window, event)
+            Self::handle_window_event(self.application, &mut self.window, &mut self.input, event)
         });
     }
 
     fn handle_window_event(
         application: &mut dyn Application,
         window: &mut Window,
+        input: &mut InputState,
         event: WindowEvent,
     ) {
         let pos = window.get_cursor_pos();
@@ -85,27 +100,56 @@ impl<'a> WindowContext<'a> {
             glfw::WindowEvent::FramebufferSize(width, height) => unsafe {
                 gl::Viewport(0, 0, width, height);
             },
-            glfw::WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
-                window.set_should_close(true)
+            glfw::WindowEvent::Key(key, _, action, modifiers) => {
+                let Some(key) = input::Key::from_glfw_code(key as i32) else {
+                    return;
+                };
+                let modifiers = Modifiers::from_glfw_bits(modifiers.bits());
+                let event = match action {
+                    Action::Press => InputEvent::KeyPress { key, modifiers },
+                    Action::Repeat => InputEvent::KeyRepeat { key, modifiers },
+                    Action::Release => InputEvent::KeyRelease { key, modifiers },
+                };
+                application.handle_event(pos, event);
+            }
+            glfw::WindowEvent::Char(character) => {
+                application.handle_event(pos, InputEvent::Text(character));
             }
             glfw::WindowEvent::CursorPos(_, _) => {
-                application.handle_event(pos, MouseEvent::Movement)
+                let delta = (pos.0 - input.cursor.0, pos.1 - input.cursor.1);
+                input.cursor = pos;
+                let event = match input.held_buttons.first() {
+                    Some(button) => InputEvent::Drag {
+                        button: *button,
+                        delta,
+                    },
+                    None => InputEvent::MouseMove,
+                };
+                application.handle_event(pos, event)
             }
-            glfw::WindowEvent::MouseButton(button, action, _modifiers) => {
-                if action == Action::Press {
-                    match button {
-                        glfw::MouseButtonLeft => {
-                            application.handle_event(pos, MouseEvent::LeftClick);
-                        }
-                        glfw::MouseButtonRight => {
-                            application.handle_event(pos, MouseEvent::RightClick);
-                        }
-                        _ => {}
+            glfw::WindowEvent::MouseButton(button, action, modifiers) => {
+                let button = match button {
+                    glfw::MouseButtonLeft => MouseButton::Left,
+                    glfw::MouseButtonRight => MouseButton::Right,
+                    glfw::MouseButtonMiddle => MouseButton::Middle,
+                    _ => return,
+                };
+                let modifiers = Modifiers::from_glfw_bits(modifiers.bits());
+                input.held_buttons.retain(|held| *held != button);
+                match action {
+                    Action::Press => {
+                        input.held_buttons.push(button);
+                        application.handle_event(pos, InputEvent::MousePress { button, modifiers });
+                    }
+                    Action::Release => {
+                        application
+                            .handle_event(pos, InputEvent::MouseRelease { button, modifiers });
                     }
+                    Action::Repeat => {}
                 }
             }
             glfw::WindowEvent::Scroll(_, scroll) => {
-                application.handle_event(pos, MouseEvent::Scroll(scroll as f32));
+                application.handle_event(pos, InputEvent::Scroll(scroll as f32));
             }
             _ => (),
         }